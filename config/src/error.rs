@@ -0,0 +1,85 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+/// Position of a token in the source, lines and columns start at 1.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub len: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub expected: String,
+    pub found: String,
+    pub span: Span,
+    /// Source line the error points to
+    pub source_line: String,
+}
+
+impl ParseError {
+    /// Render the error with the offending line and a caret under the token.
+    pub fn render(&self) -> String {
+        let line = self.span.line.to_string();
+        let pad = " ".repeat(line.len());
+        format!(
+            "{}\n{} --> {}:{}\n{} |\n{} | {}\n{} | {}{}",
+            self,
+            pad,
+            self.span.line,
+            self.span.column,
+            pad,
+            line,
+            self.source_line,
+            pad,
+            " ".repeat(self.span.column.saturating_sub(1)),
+            "^".repeat(self.span.len.max(1)),
+        )
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected {}, found {}", self.expected, self.found)
+    }
+}
+
+impl error::Error for ParseError {}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Parse(ParseError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "io error: {}", e),
+            Error::Parse(e) => write!(f, "{}", e.render()),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Parse(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        Error::Parse(err)
+    }
+}
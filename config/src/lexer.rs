@@ -1,15 +1,25 @@
+use error::Span;
 use std::iter::Peekable;
 use std::str::Chars;
 use token::Token;
 
 pub struct Lexer<'a> {
+    src: &'a str,
     buf: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+    /// Opening of a block comment which is never closed
+    unterminated: Option<Span>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(buf: &'a str) -> Self {
         Lexer {
+            src: buf,
             buf: buf.chars().peekable(),
+            line: 1,
+            column: 1,
+            unterminated: None,
         }
     }
 
@@ -18,17 +28,103 @@ impl<'a> Lexer<'a> {
     }
 
     fn eat(&mut self) -> Option<char> {
-        self.buf.next()
+        let c = self.buf.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    /// Text of the given line, without the line ending.
+    pub fn source_line(&self, line: usize) -> &'a str {
+        self.src.lines().nth(line.saturating_sub(1)).unwrap_or("")
+    }
+
+    /// Span of the end of input, used to report unexpected EOF.
+    pub fn eof_span(&mut self) -> Span {
+        self.skip_whitespace();
+        Span {
+            line: self.line,
+            column: self.column,
+            len: 1,
+        }
+    }
+
+    /// Character after the next one.
+    fn peek2(&self) -> Option<char> {
+        let mut buf = self.buf.clone();
+        buf.next();
+        buf.next()
+    }
+
+    /// Skip whitespaces and comments: `// line`, `; line` and `/* block */`.
+    fn skip_whitespace(&mut self) {
+        loop {
+            match (self.peek(), self.peek2()) {
+                (Some(c), _) if c.is_whitespace() => {
+                    self.eat();
+                }
+                (Some(';'), _) | (Some('/'), Some('/')) => {
+                    while self.peek().map(|c| c != '\n').unwrap_or(false) {
+                        self.eat();
+                    }
+                }
+                (Some('/'), Some('*')) => {
+                    let (line, column) = (self.line, self.column);
+                    self.eat();
+                    self.eat();
+                    loop {
+                        match self.eat() {
+                            Some('*') if self.peek() == Some('/') => {
+                                self.eat();
+                                break;
+                            }
+                            Some(_) => {}
+                            None => {
+                                self.unterminated = Some(Span {
+                                    line,
+                                    column,
+                                    len: 2,
+                                });
+                                break;
+                            }
+                        }
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Same as `next_token` but also returns the position of the token.
+    pub fn next_spanned(&mut self) -> Option<(Token, Span)> {
+        self.skip_whitespace();
+        if let Some(span) = self.unterminated.take() {
+            return Some((Token::UnterminatedComment, span));
+        }
+        let (line, column) = (self.line, self.column);
+        let token = self.next_token()?;
+        let len = if self.line == line {
+            self.column - column
+        } else {
+            1
+        };
+        Some((token, Span { line, column, len }))
     }
 
     pub fn next_token(&mut self) -> Option<Token> {
-        while self.peek().map(|c| c.is_whitespace()).unwrap_or(false) {
-            self.eat();
+        self.skip_whitespace();
+        if self.unterminated.take().is_some() {
+            return Some(Token::UnterminatedComment);
         }
         match self.peek()? {
             c if is_start_of_path(c) => self.lex_path(),
             c if c.is_alphabetic() => self.lex_ident(),
             c if c.is_numeric() => self.lex_number(),
+            '-' if self.peek2().map(|c| c.is_numeric()).unwrap_or(false) => self.lex_number(),
             '"' => self.lex_str(),
             '#' => self.lex_hex(),
             '{' => self.lex_simple(Token::LBrace),
@@ -36,7 +132,7 @@ impl<'a> Lexer<'a> {
             '(' => self.lex_simple(Token::LParen),
             ')' => self.lex_simple(Token::RParen),
             ',' => self.lex_simple(Token::Comma),
-            _ => None,
+            c => self.lex_simple(Token::Unknown(c)),
         }
     }
 
@@ -44,10 +140,11 @@ impl<'a> Lexer<'a> {
         let mut path = String::new();
         // TODO: allow for escaped whitespace
 
-        while let Some(c) = self.eat() {
+        while let Some(c) = self.peek() {
             if c.is_whitespace() {
                 break;
             }
+            self.eat();
             if c == '\\' && self.peek() == Some(' ') {
                 path.push(self.eat().unwrap());
             } else {
@@ -69,9 +166,20 @@ impl<'a> Lexer<'a> {
         Some(Token::Ident(ident))
     }
 
+    /// Lex `12`, `-0.5` or `20%`.
     fn lex_number(&mut self) -> Option<Token> {
         let mut number = String::new();
-        while self.peek().map(|c| c.is_numeric()).unwrap_or(false) {
+        if self.peek() == Some('-') {
+            number.push(self.eat().unwrap());
+        }
+        while self
+            .peek()
+            .map(|c| c.is_numeric() || c == '.')
+            .unwrap_or(false)
+        {
+            number.push(self.eat().unwrap());
+        }
+        if self.peek() == Some('%') {
             number.push(self.eat().unwrap());
         }
         Some(Token::Number(number))
@@ -82,7 +190,7 @@ impl<'a> Lexer<'a> {
         if self.eat()? != '#' {
             return None;
         }
-        while self.peek().map(|c| is_hex(c)).unwrap_or(false) {
+        while self.peek().map(is_hex).unwrap_or(false) {
             hex.push(self.eat().unwrap());
         }
         Some(Token::Hex(hex))
@@ -127,7 +235,7 @@ impl<'a> Iterator for Lexer<'a> {
 }
 
 fn is_hex(c: char) -> bool {
-    c.is_numeric() || 'a' <= c && c <= 'f' || 'A' <= c && c <= 'F'
+    c.is_numeric() || ('a'..='f').contains(&c) || ('A'..='F').contains(&c)
 }
 
 fn is_start_of_path(c: char) -> bool {
@@ -140,7 +248,7 @@ mod unit_tests {
 
     fn eval(buf: &str, tokens: &[Token]) {
         let mut lexer = Lexer::new(buf);
-        for token in tokens.into_iter() {
+        for token in tokens.iter() {
             assert_eq!(lexer.next_token().as_ref(), Some(token));
         }
         assert_eq!(lexer.next_token(), None);
@@ -193,4 +301,81 @@ mod unit_tests {
             ],
         );
     }
+
+    #[test]
+    fn comments() {
+        eval(
+            "// header comment
+            colors { ; inline comment
+                /* block
+                   comment */ cursor #ff00ff // trailing
+                background /* inline */ #abc
+                ; the end
+            }",
+            &[
+                Token::Ident("colors".into()),
+                Token::LBrace,
+                Token::Ident("cursor".into()),
+                Token::Hex("ff00ff".into()),
+                Token::Ident("background".into()),
+                Token::Hex("abc".into()),
+                Token::RBrace,
+            ],
+        );
+    }
+
+    #[test]
+    fn unterminated_comment() {
+        let mut lexer = Lexer::new("colors {}\n  /* never closed\n}");
+        lexer.next_token();
+        lexer.next_token();
+        lexer.next_token();
+        assert_eq!(
+            lexer.next_spanned(),
+            Some((
+                Token::UnterminatedComment,
+                Span {
+                    line: 2,
+                    column: 3,
+                    len: 2
+                }
+            ))
+        );
+        assert_eq!(lexer.next_spanned(), None);
+    }
+
+    #[test]
+    fn paths_are_not_comments() {
+        eval(
+            "output /tmp/x ./y",
+            &[
+                Token::Ident("output".into()),
+                Token::Path("/tmp/x".into()),
+                Token::Path("./y".into()),
+            ],
+        );
+    }
+
+    #[test]
+    fn numbers() {
+        eval(
+            "lighten(black, 20%) mix(red, blue, -0.5)",
+            &[
+                Token::Ident("lighten".into()),
+                Token::LParen,
+                Token::Ident("black".into()),
+                Token::Comma,
+                Token::Number("20%".into()),
+                Token::RParen,
+                Token::Ident("mix".into()),
+                Token::LParen,
+                Token::Ident("red".into()),
+                Token::Comma,
+                Token::Ident("blue".into()),
+                Token::Comma,
+                Token::Number("-0.5".into()),
+                Token::RParen,
+            ],
+        );
+    }
 }
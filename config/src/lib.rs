@@ -1,26 +1,44 @@
+pub mod error;
 pub mod lexer;
 pub mod map;
 pub mod parser;
 pub mod token;
 
+pub use error::{Error, ParseError, Span};
 use lexer::Lexer;
 use map::Map;
 use parser::Parser;
 use std::fs::File;
-use std::io::{Read, Result};
+use std::io::Read;
 use std::path::Path;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// Functions usable as values, with their parameters: `c` for a color and `n`
+/// for a number.
+pub const FUNCTIONS: &[(&str, &str)] = &[
+    ("hsl", "nnn"),
+    ("hsv", "nnn"),
+    ("lighten", "cn"),
+    ("darken", "cn"),
+    ("mix", "ccn"),
+    ("shade", "cn"),
+];
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Value {
     Hex(u32),
     Number(u8),
+    /// Number that does not fit in `Number`, percentages are divided by 100
+    Float(f64),
     RGB(u8, u8, u8),
+    RGBA(u8, u8, u8, u8),
     Str(String),
     Path(String),
     Section(Section),
+    /// Call to one of `FUNCTIONS`
+    Call(String, Vec<Value>),
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Section(Map<Value>);
 
 impl Section {
@@ -33,7 +51,7 @@ impl Section {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Config {
     sections: Map<Section>,
 }
@@ -48,15 +66,15 @@ impl Config {
     }
 }
 
-pub fn read_config<P: AsRef<Path>>(path: P) -> Result<Option<Config>> {
+pub fn read_config<P: AsRef<Path>>(path: P) -> Result<Config, Error> {
     let mut file = File::open(path.as_ref())?;
     read_stream(&mut file)
 }
 
-pub fn read_stream<R: Read>(reader: &mut R) -> Result<Option<Config>> {
+pub fn read_stream<R: Read>(reader: &mut R) -> Result<Config, Error> {
     let mut buf = String::new();
     reader.read_to_string(&mut buf)?;
     let mut lexer = Lexer::new(&buf);
     let mut parser = Parser::new(&mut lexer);
-    Ok(parser.parse())
+    Ok(parser.parse()?)
 }
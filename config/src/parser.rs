@@ -1,119 +1,277 @@
-use super::{Config, Section, Value};
+use super::{Config, Section, Value, FUNCTIONS};
+use error::{ParseError, Span};
 use lexer::Lexer;
 use map::Map;
-use std::iter::Peekable;
 use token::Token;
 
 pub struct Parser<'a> {
-    lexer: Peekable<&'a mut Lexer<'a>>,
+    lexer: &'a mut Lexer<'a>,
+    peeked: Option<Option<(Token, Span)>>,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(lexer: &'a mut Lexer<'a>) -> Self {
         Parser {
-            lexer: lexer.peekable(),
+            lexer,
+            peeked: None,
         }
     }
 
     fn peek(&mut self) -> Option<Token> {
-        self.lexer.peek().cloned()
+        if self.peeked.is_none() {
+            self.peeked = Some(self.lexer.next_spanned());
+        }
+        self.peeked
+            .as_ref()
+            .and_then(|t| t.as_ref())
+            .map(|(t, _)| t.clone())
+    }
+
+    fn eat(&mut self) -> Option<(Token, Span)> {
+        match self.peeked.take() {
+            Some(t) => t,
+            None => self.lexer.next_spanned(),
+        }
+    }
+
+    fn error_at(&self, span: Span, expected: &str, found: String) -> ParseError {
+        ParseError {
+            expected: expected.to_owned(),
+            found,
+            span,
+            source_line: self.lexer.source_line(span.line).to_owned(),
+        }
+    }
+
+    fn error(&mut self, expected: &str, found: Option<(Token, Span)>) -> ParseError {
+        let (found, span) = match found {
+            Some((t, span)) => (t.to_string(), span),
+            None => ("end of input".to_owned(), self.lexer.eof_span()),
+        };
+        ParseError {
+            expected: expected.to_owned(),
+            found,
+            span,
+            source_line: self.lexer.source_line(span.line).to_owned(),
+        }
     }
 
-    fn eat(&mut self) -> Option<Token> {
-        self.lexer.next()
+    /// Eat the next token and fail if it is not `expected`.
+    fn expect(&mut self, expected: Token) -> Result<Span, ParseError> {
+        match self.eat() {
+            Some((ref t, span)) if *t == expected => Ok(span),
+            t => Err(self.error(&expected.to_string(), t)),
+        }
     }
 
-    pub fn parse(&mut self) -> Option<Config> {
+    pub fn parse(&mut self) -> Result<Config, ParseError> {
         let mut sections = Map::new();
-        loop {
-            let name = match self.peek() {
-                Some(Token::Ident(s)) => s,
-                _ => break,
-            };
+        while let Some(Token::Ident(name)) = self.peek() {
             self.eat();
             sections.insert(name, self.parse_section()?);
         }
-        Some(Config::new(sections))
+        match self.eat() {
+            None => Ok(Config::new(sections)),
+            t => Err(self.error("section name", t)),
+        }
     }
 
-    fn parse_section(&mut self) -> Option<Section> {
-        if self.eat() != Some(Token::LBrace) {
-            return None;
-        }
+    fn parse_section(&mut self) -> Result<Section, ParseError> {
+        self.expect(Token::LBrace)?;
         let mut values = Map::new();
-        while self.peek() != Some(Token::RBrace) {
-            let name = match self.eat()? {
-                Token::Ident(s) => s,
-                _ => return None,
+        loop {
+            let name = match self.eat() {
+                Some((Token::RBrace, _)) => break,
+                Some((Token::Ident(s), _)) => s,
+                t => return Err(self.error("field name or `}`", t)),
             };
             let value = self.parse_value()?;
             values.insert(name, value);
         }
-        self.eat();
-        Some(Section::new(values))
-    }
-
-    fn parse_value(&mut self) -> Option<Value> {
-        match self.peek()? {
-            Token::LBrace => Some(Value::Section(self.parse_section()?)),
-            Token::Ident(s) => {
-                self.eat()?;
-                match s.as_str() {
-                    "rgb" => {
-                        let params = self.parse_params()?;
-                        if params.len() != 3 {
-                            return None;
-                        }
-                        let params: Option<Vec<u8>> = params
-                            .into_iter()
-                            .map(|v| match v {
-                                Value::Hex(v) => Some((v & 255) as u8),
-                                Value::Number(v) => Some(v),
-                                _ => None,
-                            }).collect();
-                        let params = params?;
-                        Some(Value::RGB(params[0], params[1], params[2]))
+        Ok(Section::new(values))
+    }
+
+    fn parse_value(&mut self) -> Result<Value, ParseError> {
+        if let Some(Token::LBrace) = self.peek() {
+            return Ok(Value::Section(self.parse_section()?));
+        }
+        match self.eat() {
+            Some((Token::Ident(s), span)) => match s.as_str() {
+                "rgb" => self.parse_rgb(span, false),
+                "rgba" => self.parse_rgb(span, true),
+                _ if self.peek() == Some(Token::LParen) => self.parse_call(s, span),
+                _ => Ok(Value::Str(s)),
+            },
+            Some((Token::Str(s), _)) => Ok(Value::Str(s)),
+            Some((Token::Path(p), _)) => Ok(Value::Path(p)),
+            Some((Token::Hex(h), span)) => self.hex(h, span),
+            Some((Token::Number(n), span)) => self.number(n, span),
+            t => Err(self.error("value", t)),
+        }
+    }
+
+    /// Parse `rgb(r, g, b)` or `rgba(r, g, b, a)`. The alpha is a fraction like the
+    /// other percent-like arguments: `0.5`, `50%` and `50` are the same, and `1` is
+    /// opaque.
+    fn parse_rgb(&mut self, span: Span, alpha: bool) -> Result<Value, ParseError> {
+        let (count, function) = if alpha { (4, "rgba") } else { (3, "rgb") };
+        let params = self.parse_params()?;
+        if params.len() != count {
+            return Err(self.error_at(
+                span,
+                &format!("{} parameters for `{}`", count, function),
+                params.len().to_string(),
+            ));
+        }
+        let mut rgba = [255; 4];
+        for (i, (value, span)) in params.into_iter().enumerate() {
+            let alpha = match value {
+                Value::Number(v) if i == 3 => Some(f64::from(v)),
+                Value::Float(v) if i == 3 => Some(v),
+                _ => None,
+            };
+            rgba[i] = match (alpha, value) {
+                (Some(a), value) => {
+                    let a = if a > 1.0 { a / 100.0 } else { a };
+                    if !(0.0..=1.0).contains(&a) {
+                        let found = format!("{:?}", value);
+                        return Err(self.error_at(span, "alpha between 0 and 1", found));
                     }
-                    _ => Some(Value::Str(s)),
+                    (a * 255.0).round() as u8
                 }
+                (None, Value::Hex(v)) => (v & 255) as u8,
+                (None, Value::Number(v)) => v,
+                (None, v) => return Err(self.error_at(span, "number", format!("{:?}", v))),
+            };
+        }
+        if alpha {
+            Ok(Value::RGBA(rgba[0], rgba[1], rgba[2], rgba[3]))
+        } else {
+            Ok(Value::RGB(rgba[0], rgba[1], rgba[2]))
+        }
+    }
+
+    /// Parse a call to `rgb` or to one of `FUNCTIONS` and check its parameters.
+    fn parse_call(&mut self, function: String, span: Span) -> Result<Value, ParseError> {
+        if function == "rgb" || function == "rgba" {
+            return self.parse_rgb(span, function == "rgba");
+        }
+        let signature = match FUNCTIONS.iter().find(|(name, _)| *name == function) {
+            Some((_, signature)) => signature,
+            None => {
+                return Err(self.error_at(
+                    span,
+                    "color function",
+                    format!("identifier `{}`", function),
+                ))
             }
-            Token::Str(s) => {
-                self.eat()?;
-                Some(Value::Str(s))
-            }
-            Token::Hex(s) => {
-                self.eat()?;
-                Some(Value::Hex(u32::from_str_radix(&s, 16).ok()?))
-            }
-            Token::Path(p) => {
-                self.eat()?;
-                Some(Value::Path(p))
+        };
+        let params = self.parse_params()?;
+        if params.len() != signature.len() {
+            return Err(self.error_at(
+                span,
+                &format!("{} parameters for `{}`", signature.len(), function),
+                params.len().to_string(),
+            ));
+        }
+        let mut values = Vec::new();
+        for ((value, span), kind) in params.into_iter().zip(signature.chars()) {
+            let valid = match value {
+                Value::Number(_) | Value::Float(_) => kind == 'n',
+                Value::Str(_)
+                | Value::Hex(_)
+                | Value::RGB(..)
+                | Value::RGBA(..)
+                | Value::Call(..) => kind == 'c',
+                _ => false,
+            };
+            if !valid {
+                let expected = if kind == 'n' { "number" } else { "color" };
+                return Err(self.error_at(span, expected, format!("{:?}", value)));
             }
-            t => unimplemented!("unimplemented parsing expression for token {:?}", t),
+            values.push(value);
         }
+        Ok(Value::Call(function, values))
     }
 
-    fn parse_params(&mut self) -> Option<Vec<Value>> {
+    /// Parse `#rrggbb`, `#rrggbbaa` and their `#rgb` and `#rgba` shorthands.
+    fn hex(&mut self, hex: String, span: Span) -> Result<Value, ParseError> {
+        let long: String = match hex.len() {
+            3 | 4 => hex.chars().flat_map(|c| vec![c, c]).collect(),
+            6 | 8 => hex.clone(),
+            _ => String::new(),
+        };
+        match (long.len(), u32::from_str_radix(&long, 16)) {
+            (6, Ok(h)) => Ok(Value::Hex(h)),
+            (8, Ok(h)) => Ok(Value::RGBA(
+                (h >> 24) as u8,
+                (h >> 16) as u8,
+                (h >> 8) as u8,
+                h as u8,
+            )),
+            _ => Err(self.error(
+                "hexadecimal color with 3, 4, 6 or 8 digits",
+                Some((Token::Hex(hex), span)),
+            )),
+        }
+    }
+
+    fn number(&mut self, number: String, span: Span) -> Result<Value, ParseError> {
+        match number.parse() {
+            Ok(n) => Ok(Value::Number(n)),
+            Err(_) => Err(self.error(
+                "number between 0 and 255",
+                Some((Token::Number(number), span)),
+            )),
+        }
+    }
+
+    /// Number parameter, `Float` is used when it does not fit in a byte.
+    fn param_number(&mut self, number: String, span: Span) -> Result<Value, ParseError> {
+        if let Ok(n) = number.parse() {
+            return Ok(Value::Number(n));
+        }
+        let parsed = if number.ends_with('%') {
+            number[..number.len() - 1].parse().map(|n: f64| n / 100.0)
+        } else {
+            number.parse()
+        };
+        match parsed {
+            Ok(n) => Ok(Value::Float(n)),
+            Err(_) => Err(self.error("number", Some((Token::Number(number), span)))),
+        }
+    }
+
+    fn parse_params(&mut self) -> Result<Vec<(Value, Span)>, ParseError> {
         let mut params = Vec::new();
-        if self.eat() != Some(Token::LParen) {
-            return None;
-        }
-        while let Some(t) = self.eat() {
-            match t {
-                Token::Ident(s) | Token::Str(s) => params.push(Value::Str(s)),
-                Token::Hex(s) => params.push(Value::Hex(u32::from_str_radix(&s, 16).ok()?)),
-                Token::Number(s) => params.push(Value::Number(s.parse().ok()?)),
-                Token::Path(s) => params.push(Value::Path(s)),
-                Token::RParen => break,
-                _ => return None,
-            }
-            match self.eat()? {
-                Token::Comma => continue,
-                Token::RParen => break,
-                _ => return None,
+        self.expect(Token::LParen)?;
+        if let Some(Token::RParen) = self.peek() {
+            self.eat();
+            return Ok(params);
+        }
+        loop {
+            let param = match self.eat() {
+                Some((Token::Ident(s), span)) => {
+                    if self.peek() == Some(Token::LParen) {
+                        (self.parse_call(s, span)?, span)
+                    } else {
+                        (Value::Str(s), span)
+                    }
+                }
+                Some((Token::Str(s), span)) => (Value::Str(s), span),
+                Some((Token::Hex(s), span)) => (self.hex(s, span)?, span),
+                Some((Token::Number(s), span)) => (self.param_number(s, span)?, span),
+                Some((Token::Path(s), span)) => (Value::Path(s), span),
+                t => return Err(self.error("parameter", t)),
+            };
+            params.push(param);
+            match self.eat() {
+                Some((Token::Comma, _)) => continue,
+                Some((Token::RParen, _)) => break,
+                t => return Err(self.error("`,` or `)`", t)),
             }
         }
-        Some(params)
+        Ok(params)
     }
 }
 
@@ -125,25 +283,95 @@ mod unit_tests {
     fn expect_value(buf: &str, expected: Value) {
         let mut lexer = Lexer::new(buf);
         let mut parser = Parser::new(&mut lexer);
-        assert_eq!(parser.parse_value(), Some(expected));
+        assert_eq!(parser.parse_value(), Ok(expected));
     }
 
     fn expect_section(buf: &str, expected: Section) {
         let mut lexer = Lexer::new(buf);
         let mut parser = Parser::new(&mut lexer);
-        assert_eq!(parser.parse_section(), Some(expected));
+        assert_eq!(parser.parse_section(), Ok(expected));
     }
 
     fn expect_config(buf: &str, expected: Config) {
         let mut lexer = Lexer::new(buf);
         let mut parser = Parser::new(&mut lexer);
-        assert_eq!(parser.parse(), Some(expected));
+        assert_eq!(parser.parse(), Ok(expected));
+    }
+
+    fn expect_error(buf: &str, line: usize, column: usize, message: &str) {
+        let mut lexer = Lexer::new(buf);
+        let mut parser = Parser::new(&mut lexer);
+        let err = parser.parse().unwrap_err();
+        assert_eq!((err.span.line, err.span.column), (line, column));
+        assert_eq!(err.to_string(), message);
     }
 
     // TODO:
     #[test]
     fn rgb() {
         expect_value("rgb(10, 200, 230)", Value::RGB(10, 200, 230));
+        expect_value("rgba(10, 200, 230, 0.5)", Value::RGBA(10, 200, 230, 128));
+        expect_value("rgba(10, 200, 230, 1)", Value::RGBA(10, 200, 230, 255));
+        expect_value("rgba(10, 200, 230, 50%)", Value::RGBA(10, 200, 230, 128));
+        expect_value("rgba(10, 200, 230, 50)", Value::RGBA(10, 200, 230, 128));
+    }
+
+    #[test]
+    fn hex() {
+        expect_value("#191d26", Value::Hex(0x191d26));
+        expect_value("#fa0", Value::Hex(0xffaa00));
+        expect_value("#191d26cc", Value::RGBA(0x19, 0x1d, 0x26, 0xcc));
+        expect_value("#fa08", Value::RGBA(0xff, 0xaa, 0x00, 0x88));
+    }
+
+    #[test]
+    fn functions() {
+        expect_value(
+            "lighten(black, 20%)",
+            Value::Call(
+                "lighten".into(),
+                vec![Value::Str("black".into()), Value::Float(0.2)],
+            ),
+        );
+        expect_value(
+            "hsl(300, 50, 0.4)",
+            Value::Call(
+                "hsl".into(),
+                vec![Value::Float(300.0), Value::Number(50), Value::Float(0.4)],
+            ),
+        );
+        expect_value(
+            "mix(darken(red, 10%), #ffffff, 0.5)",
+            Value::Call(
+                "mix".into(),
+                vec![
+                    Value::Call(
+                        "darken".into(),
+                        vec![Value::Str("red".into()), Value::Float(0.1)],
+                    ),
+                    Value::Hex(0xffffff),
+                    Value::Float(0.5),
+                ],
+            ),
+        );
+        expect_value(
+            "lighten(rgb(1, 2, 3), 20%)",
+            Value::Call(
+                "lighten".into(),
+                vec![Value::RGB(1, 2, 3), Value::Float(0.2)],
+            ),
+        );
+        expect_value(
+            "mix(rgba(1, 2, 3, 0.5), black, 0.5)",
+            Value::Call(
+                "mix".into(),
+                vec![
+                    Value::RGBA(1, 2, 3, 128),
+                    Value::Str("black".into()),
+                    Value::Float(0.5),
+                ],
+            ),
+        );
     }
 
     #[test]
@@ -213,4 +441,82 @@ mod unit_tests {
             ),
         );
     }
+
+    #[test]
+    fn errors() {
+        expect_error(
+            "colors {\n    black #191d26\n    red = #ea5965\n}",
+            3,
+            9,
+            "expected value, found unexpected character `=`",
+        );
+        expect_error(
+            "colors {\n    black",
+            2,
+            10,
+            "expected value, found end of input",
+        );
+        expect_error(
+            "x11 {}\n#fff",
+            2,
+            1,
+            "expected section name, found color `#fff`",
+        );
+        expect_error(
+            "colors {\n    black #000000\n}\n/* unused\nx11 {}",
+            4,
+            1,
+            "expected section name, found unterminated comment `/*`",
+        );
+        expect_error(
+            "colors { red rgb(1, 2) }",
+            1,
+            14,
+            "expected 3 parameters for `rgb`, found 2",
+        );
+        expect_error(
+            "colors { red rgba(1, 2, 3, 200) }",
+            1,
+            28,
+            "expected alpha between 0 and 1, found Number(200)",
+        );
+        expect_error(
+            "colors { red tint(black, 10%) }",
+            1,
+            14,
+            "expected color function, found identifier `tint`",
+        );
+        expect_error(
+            "colors { red lighten(10%, black) }",
+            1,
+            22,
+            "expected color, found Float(0.1)",
+        );
+        expect_error(
+            "colors { red mix(black, white) }",
+            1,
+            14,
+            "expected 3 parameters for `mix`, found 2",
+        );
+        expect_error(
+            "colors { red #12345 }",
+            1,
+            14,
+            "expected hexadecimal color with 3, 4, 6 or 8 digits, found color `#12345`",
+        );
+    }
+
+    #[test]
+    fn render() {
+        let mut lexer = Lexer::new("colors {\n    black 300\n}");
+        let mut parser = Parser::new(&mut lexer);
+        assert_eq!(
+            parser.parse().unwrap_err().render(),
+            "expected number between 0 and 255, found number `300`
+  --> 2:11
+  |
+2 |     black 300
+  |           ^^^"
+        );
+    }
 }
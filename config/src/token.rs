@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Token {
     Ident(String),
@@ -10,4 +12,26 @@ pub enum Token {
     LParen,
     RParen,
     Comma,
+    Unknown(char),
+    /// Block comment opened and never closed
+    UnterminatedComment,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Ident(s) => write!(f, "identifier `{}`", s),
+            Token::Str(s) => write!(f, "string \"{}\"", s),
+            Token::Hex(s) => write!(f, "color `#{}`", s),
+            Token::Path(s) => write!(f, "path `{}`", s),
+            Token::Number(s) => write!(f, "number `{}`", s),
+            Token::LBrace => write!(f, "`{{`"),
+            Token::RBrace => write!(f, "`}}`"),
+            Token::LParen => write!(f, "`(`"),
+            Token::RParen => write!(f, "`)`"),
+            Token::Comma => write!(f, "`,`"),
+            Token::Unknown(c) => write!(f, "unexpected character `{}`", c),
+            Token::UnterminatedComment => write!(f, "unterminated comment `/*`"),
+        }
+    }
 }
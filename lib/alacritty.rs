@@ -0,0 +1,262 @@
+use super::{splice_file, Applied, Color, Error, GetResult, Getter, State, Theme};
+use config::Section;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use x11::{mapped_color, ANSI};
+
+const AVAILABLE_FIELDS: &[&str] = &[
+    "format",
+    "output",
+    "splice",
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright_black",
+    "bright_red",
+    "bright_green",
+    "bright_yellow",
+    "bright_blue",
+    "bright_magenta",
+    "bright_cyan",
+    "bright_white",
+    "foreground",
+    "background",
+    "cursor",
+    "cursor_text",
+    "selection_foreground",
+    "selection_background",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// `alacritty.toml`, since Alacritty 0.13
+    Toml,
+    /// `alacritty.yml`, for older versions
+    Yaml,
+}
+
+#[derive(Debug)]
+pub struct Alacritty {
+    format: Format,
+    /// Standalone file, to be imported by the main config
+    output: Option<PathBuf>,
+    /// Existing config in which the colors are spliced
+    splice: Option<PathBuf>,
+    /// Tables and their colors, in order
+    tables: Vec<(String, Vec<(String, Color)>)>,
+}
+
+impl Default for Alacritty {
+    fn default() -> Self {
+        Alacritty {
+            format: Format::Toml,
+            output: None,
+            splice: None,
+            tables: Vec::new(),
+        }
+    }
+}
+
+impl Alacritty {
+    pub fn new() -> Self {
+        Alacritty::default()
+    }
+
+    fn default_output(&self) -> PathBuf {
+        let file = match self.format {
+            Format::Toml => "themer.toml",
+            Format::Yaml => "themer.yml",
+        };
+        ::expand_home("~/.config/alacritty").join(file)
+    }
+}
+
+/// YAML for paths ending with `.yml` or `.yaml`.
+fn format_of(path: &Path) -> Format {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("yml") | Some("yaml") => Format::Yaml,
+        _ => Format::Toml,
+    }
+}
+
+impl Theme for Alacritty {
+    fn name(&self) -> &str {
+        "alacritty"
+    }
+
+    fn available_fields(&self) -> &[&str] {
+        AVAILABLE_FIELDS
+    }
+
+    fn create(&mut self, state: &State, section: &Section) -> Result<(), Error> {
+        self.output = section.get_path(state, "output").to_option();
+        self.splice = section.get_path(state, "splice").to_option();
+        self.format = match section.get_str(state, "format") {
+            GetResult::Ok(ref f) if f == "yaml" || f == "yml" => Format::Yaml,
+            GetResult::Ok(_) => Format::Toml,
+            _ => self
+                .splice
+                .as_ref()
+                .or(self.output.as_ref())
+                .map(|p| format_of(p))
+                .unwrap_or(Format::Toml),
+        };
+        let color = |name: &str| mapped_color(state, section, name);
+        let table = |keys: &[(&str, &str)]| -> Vec<(String, Color)> {
+            keys.iter()
+                .filter_map(|(key, name)| color(name).map(|c| (key.to_string(), c)))
+                .collect()
+        };
+        let (normal, bright) = ANSI.split_at(8);
+        let normal: Vec<(&str, &str)> = normal.iter().map(|n| (*n, *n)).collect();
+        let bright: Vec<(&str, &str)> = normal
+            .iter()
+            .zip(bright)
+            .map(|((key, _), name)| (*key, *name))
+            .collect();
+        let mut tables = vec![
+            (
+                "primary",
+                table(&[("background", "background"), ("foreground", "foreground")]),
+            ),
+            (
+                "cursor",
+                table(&[("text", "cursor_text"), ("cursor", "cursor")]),
+            ),
+            ("normal", table(&normal)),
+            ("bright", table(&bright)),
+            (
+                "selection",
+                table(&[
+                    ("text", "selection_foreground"),
+                    ("background", "selection_background"),
+                ]),
+            ),
+        ];
+        tables.retain(|(_, colors)| !colors.is_empty());
+        self.tables = tables
+            .into_iter()
+            .map(|(name, colors)| (name.to_owned(), colors))
+            .collect();
+        Ok(())
+    }
+
+    fn generated(&self) -> Result<String, Error> {
+        let hex = ::template::Format::Hex;
+        let mut buf = Vec::new();
+        match self.format {
+            Format::Toml => {
+                for (table, colors) in &self.tables {
+                    if !buf.is_empty() {
+                        buf.push(String::new());
+                    }
+                    buf.push(format!("[colors.{}]", table));
+                    for (key, c) in colors {
+                        buf.push(format!("{} = \"{}\"", key, hex.format(c)));
+                    }
+                }
+            }
+            Format::Yaml => {
+                buf.push("colors:".to_owned());
+                for (table, colors) in &self.tables {
+                    buf.push(format!("  {}:", table));
+                    for (key, c) in colors {
+                        buf.push(format!("    {}: '{}'", key, hex.format(c)));
+                    }
+                }
+            }
+        }
+        Ok(buf.join("\n") + "\n")
+    }
+
+    fn apply(&self) -> Result<Applied, Error> {
+        let generated = self.generated()?;
+        let path = match (&self.splice, &self.output) {
+            (Some(path), _) => {
+                splice_file(path, &generated, "#")?;
+                path.to_owned()
+            }
+            (None, output) => {
+                let path = output.clone().unwrap_or_else(|| self.default_output());
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                File::create(&path)?.write_all(generated.as_bytes())?;
+                path
+            }
+        };
+        // Alacritty reloads its config when it changes.
+        Ok(Applied {
+            output: path,
+            command: None,
+            status: None,
+        })
+    }
+
+    fn output(&mut self) -> Option<&PathBuf> {
+        self.output.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use test_utils::{create, state};
+
+    fn alacritty(section: &str) -> Alacritty {
+        let state = state(
+            "test",
+            &[
+                ("background", Color::rgb(0x19, 0x1d, 0x26)),
+                ("foreground", Color::rgb(0xd0, 0xce, 0xc3)),
+                ("red", Color::rgb(0xea, 0x59, 0x65)),
+                ("bright_red", Color::rgb(0xff, 0x55, 0x55)),
+            ],
+        );
+        create(Alacritty::new(), &state, section).unwrap()
+    }
+
+    #[test]
+    fn toml() {
+        assert_eq!(
+            alacritty("alacritty { color0 #000000 }")
+                .generated()
+                .unwrap(),
+            "[colors.primary]
+background = \"#191d26\"
+foreground = \"#d0cec3\"
+
+[colors.normal]
+black = \"#000000\"
+red = \"#ea5965\"
+
+[colors.bright]
+red = \"#ff5555\"
+"
+        );
+    }
+
+    #[test]
+    fn yaml() {
+        assert_eq!(
+            alacritty("alacritty { splice ~/.config/alacritty/alacritty.yml }")
+                .generated()
+                .unwrap(),
+            "colors:
+  primary:
+    background: '#191d26'
+    foreground: '#d0cec3'
+  normal:
+    red: '#ea5965'
+  bright:
+    red: '#ff5555'
+"
+        );
+    }
+}
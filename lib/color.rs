@@ -0,0 +1,663 @@
+//! Colors and their conversions between color spaces.
+//!
+//! `Color` is an 8-bit sRGB color. The other spaces use `f64` components and
+//! are converted back to `Color` with `From`, clamping out of gamut values.
+
+/// Red, green, blue and alpha components.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Color(u8, u8, u8, u8);
+
+/// sRGB without gamma, components between 0 and 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LinearRgb {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+}
+
+/// Hue in degrees, saturation and lightness between 0 and 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hsl {
+    pub h: f64,
+    pub s: f64,
+    pub l: f64,
+}
+
+/// Hue in degrees, saturation and value between 0 and 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hsv {
+    pub h: f64,
+    pub s: f64,
+    pub v: f64,
+}
+
+/// CIELAB with a D65 white point, lightness between 0 and 100.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lab {
+    pub l: f64,
+    pub a: f64,
+    pub b: f64,
+}
+
+/// Cylindrical CIELAB, hue in degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lch {
+    pub l: f64,
+    pub c: f64,
+    pub h: f64,
+}
+
+/// OKLab, lightness between 0 and 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Oklab {
+    pub l: f64,
+    pub a: f64,
+    pub b: f64,
+}
+
+/// Cylindrical OKLab, hue in degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Oklch {
+    pub l: f64,
+    pub c: f64,
+    pub h: f64,
+}
+
+impl Color {
+    pub fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color(r, g, b, 255)
+    }
+
+    pub fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color(r, g, b, a)
+    }
+
+    pub fn red(&self) -> u8 {
+        self.0
+    }
+
+    pub fn green(&self) -> u8 {
+        self.1
+    }
+
+    pub fn blue(&self) -> u8 {
+        self.2
+    }
+
+    pub fn alpha(&self) -> u8 {
+        self.3
+    }
+
+    /// Same color with an alpha between 0 and 1.
+    pub fn with_alpha(&self, alpha: f64) -> Color {
+        Color(self.0, self.1, self.2, channel(alpha))
+    }
+
+    /// Parse `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`, the `#` is optional.
+    pub fn from_hex(hex: &str) -> Option<Color> {
+        let hex = hex.trim_start_matches('#');
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digit = |i: usize| u8::from_str_radix(&hex[i..=i], 16).ok().map(|d| d * 17);
+        let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        match hex.len() {
+            3 => Some(Color(digit(0)?, digit(1)?, digit(2)?, 255)),
+            4 => Some(Color(digit(0)?, digit(1)?, digit(2)?, digit(3)?)),
+            6 => Some(Color(byte(0)?, byte(2)?, byte(4)?, 255)),
+            8 => Some(Color(byte(0)?, byte(2)?, byte(4)?, byte(6)?)),
+            _ => None,
+        }
+    }
+
+    pub fn to_linear(&self) -> LinearRgb {
+        let linear = |c: u8| {
+            let c = f64::from(c) / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        LinearRgb {
+            r: linear(self.0),
+            g: linear(self.1),
+            b: linear(self.2),
+        }
+    }
+
+    pub fn to_hsl(&self) -> Hsl {
+        let (h, max, min) = self.hue();
+        let l = (max + min) / 2.0;
+        let d = max - min;
+        let s = if d == 0.0 {
+            0.0
+        } else {
+            d / (1.0 - (2.0 * l - 1.0).abs())
+        };
+        Hsl { h, s, l }
+    }
+
+    pub fn to_hsv(&self) -> Hsv {
+        let (h, max, min) = self.hue();
+        let s = if max == 0.0 { 0.0 } else { (max - min) / max };
+        Hsv { h, s, v: max }
+    }
+
+    pub fn to_lab(&self) -> Lab {
+        self.to_linear().to_lab()
+    }
+
+    pub fn to_lch(&self) -> Lch {
+        self.to_lab().to_lch()
+    }
+
+    pub fn to_oklab(&self) -> Oklab {
+        self.to_linear().to_oklab()
+    }
+
+    pub fn to_oklch(&self) -> Oklch {
+        self.to_oklab().to_oklch()
+    }
+
+    /// Hue in degrees, maximum and minimum of the components.
+    fn hue(&self) -> (f64, f64, f64) {
+        let (r, g, b) = (
+            f64::from(self.0) / 255.0,
+            f64::from(self.1) / 255.0,
+            f64::from(self.2) / 255.0,
+        );
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let d = max - min;
+        let h = if d == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * (((g - b) / d) % 6.0)
+        } else if max == g {
+            60.0 * ((b - r) / d + 2.0)
+        } else {
+            60.0 * ((r - g) / d + 4.0)
+        };
+        (if h < 0.0 { h + 360.0 } else { h }, max, min)
+    }
+
+    pub fn from_hsl(h: f64, s: f64, l: f64) -> Color {
+        Color::from(Hsl { h, s, l })
+    }
+
+    pub fn from_hsv(h: f64, s: f64, v: f64) -> Color {
+        Color::from(Hsv { h, s, v })
+    }
+
+    /// CIEDE2000 color difference, around 1 for a just noticeable difference.
+    pub fn delta_e2000(&self, other: &Color) -> f64 {
+        self.to_lab().delta_e2000(&other.to_lab())
+    }
+
+    /// Euclidean distance in OKLab.
+    pub fn delta_ok(&self, other: &Color) -> f64 {
+        let (a, b) = (self.to_oklab(), other.to_oklab());
+        ((a.l - b.l).powi(2) + (a.a - b.a).powi(2) + (a.b - b.b).powi(2)).sqrt()
+    }
+
+    /// Closest color of the xterm 256 colors palette, from the 6x6x6 cube or the
+    /// gray ramp.
+    pub fn to_ansi256(&self) -> u8 {
+        const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+        let level = |c: u8| {
+            (0..6)
+                .min_by_key(|&i| (i32::from(LEVELS[i]) - i32::from(c)).abs())
+                .unwrap()
+        };
+        let (r, g, b) = (level(self.0), level(self.1), level(self.2));
+        let cube = Color::rgb(LEVELS[r], LEVELS[g], LEVELS[b]);
+        let mean = (u32::from(self.0) + u32::from(self.1) + u32::from(self.2)) / 3;
+        let step = (mean.saturating_sub(3) / 10).min(23) as u8;
+        let gray = Color::rgb(8 + step * 10, 8 + step * 10, 8 + step * 10);
+        let distance = |c: &Color| {
+            let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2);
+            d(c.0, self.0) + d(c.1, self.1) + d(c.2, self.2)
+        };
+        if distance(&gray) < distance(&cube) {
+            232 + step
+        } else {
+            16 + 36 * r as u8 + 6 * g as u8 + b as u8
+        }
+    }
+
+    /// WCAG 2.1 relative luminance, between 0 and 1.
+    pub fn luminance(&self) -> f64 {
+        let c = self.to_linear();
+        0.2126 * c.r + 0.7152 * c.g + 0.0722 * c.b
+    }
+
+    /// WCAG 2.1 contrast ratio, between 1 and 21.
+    pub fn contrast(&self, other: &Color) -> f64 {
+        let (a, b) = (self.luminance(), other.luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    /// APCA lightness contrast (Lc) of `self` as text on `background`, between
+    /// about -108 and 106. Negative for light text on a dark background.
+    pub fn apca(&self, background: &Color) -> f64 {
+        let luminance = |c: &Color| {
+            let y = 0.212_672_9 * (f64::from(c.0) / 255.0).powf(2.4)
+                + 0.715_152_2 * (f64::from(c.1) / 255.0).powf(2.4)
+                + 0.072_175 * (f64::from(c.2) / 255.0).powf(2.4);
+            if y < 0.022 {
+                y + (0.022 - y).powf(1.414)
+            } else {
+                y
+            }
+        };
+        let (text, background) = (luminance(self), luminance(background));
+        if (background - text).abs() < 0.0005 {
+            return 0.0;
+        }
+        let lc = if background > text {
+            let s = (background.powf(0.56) - text.powf(0.57)) * 1.14;
+            if s < 0.1 {
+                0.0
+            } else {
+                s - 0.027
+            }
+        } else {
+            let s = (background.powf(0.65) - text.powf(0.62)) * 1.14;
+            if s > -0.1 {
+                0.0
+            } else {
+                s + 0.027
+            }
+        };
+        lc * 100.0
+    }
+
+    /// Add `amount` (between -1 and 1) to the HSL lightness.
+    pub fn lighten(&self, amount: f64) -> Color {
+        let mut hsl = self.to_hsl();
+        hsl.l += amount;
+        Color::from(hsl).with_alpha_of(self)
+    }
+
+    pub fn darken(&self, amount: f64) -> Color {
+        self.lighten(-amount)
+    }
+
+    /// Add `amount` (between -1 and 1) to the HSL saturation.
+    pub fn saturate(&self, amount: f64) -> Color {
+        let mut hsl = self.to_hsl();
+        hsl.s += amount;
+        Color::from(hsl).with_alpha_of(self)
+    }
+
+    pub fn desaturate(&self, amount: f64) -> Color {
+        self.saturate(-amount)
+    }
+
+    /// Move `t` (between 0 and 1) of the way from `self` to `other`.
+    pub fn mix(&self, other: &Color, t: f64) -> Color {
+        let t = clamp(t);
+        let mix = |a: u8, b: u8| channel((f64::from(a) * (1.0 - t) + f64::from(b) * t) / 255.0);
+        Color(
+            mix(self.0, other.0),
+            mix(self.1, other.1),
+            mix(self.2, other.2),
+            mix(self.3, other.3),
+        )
+    }
+
+    pub fn invert(&self) -> Color {
+        Color(255 - self.0, 255 - self.1, 255 - self.2, self.3)
+    }
+
+    fn with_alpha_of(mut self, other: &Color) -> Color {
+        self.3 = other.3;
+        self
+    }
+}
+
+impl LinearRgb {
+    pub fn to_lab(&self) -> Lab {
+        let (r, g, b) = (self.r, self.g, self.b);
+        let x = 0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b;
+        let y = 0.212_672_9 * r + 0.715_152_2 * g + 0.072_175 * b;
+        let z = 0.019_333_9 * r + 0.119_192 * g + 0.950_304_1 * b;
+        let (fx, fy, fz) = (lab_f(x / WHITE.0), lab_f(y / WHITE.1), lab_f(z / WHITE.2));
+        Lab {
+            l: 116.0 * fy - 16.0,
+            a: 500.0 * (fx - fy),
+            b: 200.0 * (fy - fz),
+        }
+    }
+
+    pub fn to_oklab(&self) -> Oklab {
+        let (r, g, b) = (self.r, self.g, self.b);
+        let l = (0.412_221_470_8 * r + 0.536_332_536_3 * g + 0.051_445_992_9 * b).cbrt();
+        let m = (0.211_903_498_2 * r + 0.680_699_545_1 * g + 0.107_396_956_6 * b).cbrt();
+        let s = (0.088_302_461_9 * r + 0.281_718_837_6 * g + 0.629_978_700_5 * b).cbrt();
+        Oklab {
+            l: 0.210_454_255_3 * l + 0.793_617_785 * m - 0.004_072_046_8 * s,
+            a: 1.977_998_495_1 * l - 2.428_592_205 * m + 0.450_593_709_9 * s,
+            b: 0.025_904_037_1 * l + 0.782_771_766_2 * m - 0.808_675_766 * s,
+        }
+    }
+}
+
+impl Lab {
+    pub fn to_lch(&self) -> Lch {
+        let (c, h) = to_polar(self.a, self.b);
+        Lch { l: self.l, c, h }
+    }
+
+    pub fn to_linear(&self) -> LinearRgb {
+        let fy = (self.l + 16.0) / 116.0;
+        let fx = fy + self.a / 500.0;
+        let fz = fy - self.b / 200.0;
+        let (x, y, z) = (
+            lab_finv(fx) * WHITE.0,
+            lab_finv(fy) * WHITE.1,
+            lab_finv(fz) * WHITE.2,
+        );
+        LinearRgb {
+            r: 3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z,
+            g: -0.969_266 * x + 1.876_010_8 * y + 0.041_556 * z,
+            b: 0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z,
+        }
+    }
+
+    /// CIEDE2000 color difference.
+    pub fn delta_e2000(&self, other: &Lab) -> f64 {
+        let (l1, a1, b1) = (self.l, self.a, self.b);
+        let (l2, a2, b2) = (other.l, other.a, other.b);
+        let c_mean = ((a1.hypot(b1)) + (a2.hypot(b2))) / 2.0;
+        let g = 0.5 * (1.0 - (c_mean.powi(7) / (c_mean.powi(7) + 25f64.powi(7))).sqrt());
+        let (a1, a2) = (a1 * (1.0 + g), a2 * (1.0 + g));
+        let (c1, h1) = to_polar(a1, b1);
+        let (c2, h2) = to_polar(a2, b2);
+
+        let dl = l2 - l1;
+        let dc = c2 - c1;
+        let dh = if c1 * c2 == 0.0 {
+            0.0
+        } else if (h2 - h1).abs() <= 180.0 {
+            h2 - h1
+        } else if h2 - h1 > 180.0 {
+            h2 - h1 - 360.0
+        } else {
+            h2 - h1 + 360.0
+        };
+        let dh = 2.0 * (c1 * c2).sqrt() * (dh / 2.0).to_radians().sin();
+
+        let l_mean = (l1 + l2) / 2.0;
+        let c_mean = (c1 + c2) / 2.0;
+        let h_mean = if c1 * c2 == 0.0 {
+            h1 + h2
+        } else if (h1 - h2).abs() <= 180.0 {
+            (h1 + h2) / 2.0
+        } else if h1 + h2 < 360.0 {
+            (h1 + h2 + 360.0) / 2.0
+        } else {
+            (h1 + h2 - 360.0) / 2.0
+        };
+        let t = 1.0 - 0.17 * (h_mean - 30.0).to_radians().cos()
+            + 0.24 * (2.0 * h_mean).to_radians().cos()
+            + 0.32 * (3.0 * h_mean + 6.0).to_radians().cos()
+            - 0.20 * (4.0 * h_mean - 63.0).to_radians().cos();
+        let d_theta = 30.0 * (-((h_mean - 275.0) / 25.0).powi(2)).exp();
+        let rc = 2.0 * (c_mean.powi(7) / (c_mean.powi(7) + 25f64.powi(7))).sqrt();
+        let sl = 1.0 + 0.015 * (l_mean - 50.0).powi(2) / (20.0 + (l_mean - 50.0).powi(2)).sqrt();
+        let sc = 1.0 + 0.045 * c_mean;
+        let sh = 1.0 + 0.015 * c_mean * t;
+        let rt = -(2.0 * d_theta).to_radians().sin() * rc;
+
+        ((dl / sl).powi(2) + (dc / sc).powi(2) + (dh / sh).powi(2) + rt * (dc / sc) * (dh / sh))
+            .sqrt()
+    }
+}
+
+impl Lch {
+    pub fn to_lab(&self) -> Lab {
+        let (a, b) = from_polar(self.c, self.h);
+        Lab { l: self.l, a, b }
+    }
+}
+
+impl Oklab {
+    pub fn to_oklch(&self) -> Oklch {
+        let (c, h) = to_polar(self.a, self.b);
+        Oklch { l: self.l, c, h }
+    }
+
+    pub fn to_linear(&self) -> LinearRgb {
+        let l = (self.l + 0.396_337_777_4 * self.a + 0.215_803_757_3 * self.b).powi(3);
+        let m = (self.l - 0.105_561_345_8 * self.a - 0.063_854_172_8 * self.b).powi(3);
+        let s = (self.l - 0.089_484_177_5 * self.a - 1.291_485_548 * self.b).powi(3);
+        LinearRgb {
+            r: 4.076_741_662_1 * l - 3.307_711_591_3 * m + 0.230_969_929_2 * s,
+            g: -1.268_438_004_6 * l + 2.609_757_401_1 * m - 0.341_319_396_5 * s,
+            b: -0.004_196_086_3 * l - 0.703_418_614_7 * m + 1.707_614_701 * s,
+        }
+    }
+}
+
+impl Oklch {
+    pub fn to_oklab(&self) -> Oklab {
+        let (a, b) = from_polar(self.c, self.h);
+        Oklab { l: self.l, a, b }
+    }
+}
+
+impl From<LinearRgb> for Color {
+    fn from(c: LinearRgb) -> Color {
+        let gamma = |c: f64| {
+            let c = clamp(c);
+            if c <= 0.003_130_8 {
+                12.92 * c
+            } else {
+                1.055 * c.powf(1.0 / 2.4) - 0.055
+            }
+        };
+        Color(
+            channel(gamma(c.r)),
+            channel(gamma(c.g)),
+            channel(gamma(c.b)),
+            255,
+        )
+    }
+}
+
+impl From<Hsl> for Color {
+    fn from(c: Hsl) -> Color {
+        let s = clamp(c.s);
+        let l = clamp(c.l);
+        let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        let (r, g, b) = from_hue(c.h, chroma);
+        let m = l - chroma / 2.0;
+        Color(channel(r + m), channel(g + m), channel(b + m), 255)
+    }
+}
+
+impl From<Hsv> for Color {
+    fn from(c: Hsv) -> Color {
+        let s = clamp(c.s);
+        let v = clamp(c.v);
+        let chroma = v * s;
+        let (r, g, b) = from_hue(c.h, chroma);
+        let m = v - chroma;
+        Color(channel(r + m), channel(g + m), channel(b + m), 255)
+    }
+}
+
+impl From<Lab> for Color {
+    fn from(c: Lab) -> Color {
+        Color::from(c.to_linear())
+    }
+}
+
+impl From<Lch> for Color {
+    fn from(c: Lch) -> Color {
+        Color::from(c.to_lab())
+    }
+}
+
+impl From<Oklab> for Color {
+    fn from(c: Oklab) -> Color {
+        Color::from(c.to_linear())
+    }
+}
+
+impl From<Oklch> for Color {
+    fn from(c: Oklch) -> Color {
+        Color::from(c.to_oklab())
+    }
+}
+
+/// D65 reference white in XYZ, the sum of the rows of the sRGB matrix.
+const WHITE: (f64, f64, f64) = (0.950_47, 1.0, 1.088_83);
+
+fn lab_f(t: f64) -> f64 {
+    let delta: f64 = 6.0 / 29.0;
+    if t > delta.powi(3) {
+        t.cbrt()
+    } else {
+        t / (3.0 * delta.powi(2)) + 4.0 / 29.0
+    }
+}
+
+fn lab_finv(t: f64) -> f64 {
+    let delta: f64 = 6.0 / 29.0;
+    if t > delta {
+        t.powi(3)
+    } else {
+        3.0 * delta.powi(2) * (t - 4.0 / 29.0)
+    }
+}
+
+/// Chroma and hue in degrees from the two axes.
+fn to_polar(a: f64, b: f64) -> (f64, f64) {
+    let h = b.atan2(a).to_degrees();
+    (a.hypot(b), if h < 0.0 { h + 360.0 } else { h })
+}
+
+fn from_polar(c: f64, h: f64) -> (f64, f64) {
+    let h = h.to_radians();
+    (c * h.cos(), c * h.sin())
+}
+
+/// Red, green and blue without the lightness offset, from a hue and a chroma.
+fn from_hue(h: f64, chroma: f64) -> (f64, f64, f64) {
+    let h = ((h % 360.0) + 360.0) % 360.0 / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    match h as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    }
+}
+
+fn clamp(v: f64) -> f64 {
+    v.clamp(0.0, 1.0)
+}
+
+/// Convert a component between 0 and 1 to a byte.
+fn channel(v: f64) -> u8 {
+    (clamp(v) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn color() -> impl Strategy<Value = Color> {
+        (any::<u8>(), any::<u8>(), any::<u8>()).prop_map(|(r, g, b)| Color::rgb(r, g, b))
+    }
+
+    proptest! {
+        #[test]
+        fn round_trips(c in color()) {
+            prop_assert_eq!(Color::from(c.to_linear()), c.clone());
+            prop_assert_eq!(Color::from(c.to_hsl()), c.clone());
+            prop_assert_eq!(Color::from(c.to_hsv()), c.clone());
+            prop_assert_eq!(Color::from(c.to_lab()), c.clone());
+            prop_assert_eq!(Color::from(c.to_lch()), c.clone());
+            prop_assert_eq!(Color::from(c.to_oklab()), c.clone());
+            prop_assert_eq!(Color::from(c.to_oklch()), c);
+        }
+
+        #[test]
+        fn distances(a in color(), b in color()) {
+            prop_assert!(a.delta_e2000(&a) < 1e-9);
+            prop_assert!(a.delta_ok(&a) < 1e-9);
+            prop_assert!((a.delta_e2000(&b) - b.delta_e2000(&a)).abs() < 1e-9);
+            prop_assert!((a.delta_ok(&b) - b.delta_ok(&a)).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn white() {
+        let lab = Color::rgb(255, 255, 255).to_lab();
+        assert!((lab.l - 100.0).abs() < 1e-4 && lab.a.abs() < 1e-4 && lab.b.abs() < 1e-4);
+        let oklab = Color::rgb(255, 255, 255).to_oklab();
+        assert!((oklab.l - 1.0).abs() < 1e-4 && oklab.a.abs() < 1e-4 && oklab.b.abs() < 1e-4);
+    }
+
+    #[test]
+    fn ansi256() {
+        assert_eq!(Color::rgb(0, 0, 0).to_ansi256(), 16);
+        assert_eq!(Color::rgb(255, 255, 255).to_ansi256(), 231);
+        assert_eq!(Color::rgb(255, 0, 0).to_ansi256(), 196);
+        assert_eq!(Color::rgb(0x80, 0x80, 0x80).to_ansi256(), 244);
+        assert_eq!(Color::rgb(0x19, 0x1d, 0x26).to_ansi256(), 234);
+    }
+
+    #[test]
+    fn contrast() {
+        let (black, white) = (Color::rgb(0, 0, 0), Color::rgb(255, 255, 255));
+        assert!((black.contrast(&white) - 21.0).abs() < 1e-9);
+        assert!((white.contrast(&white) - 1.0).abs() < 1e-9);
+        let gray = Color::rgb(0x76, 0x76, 0x76);
+        assert!((gray.contrast(&white) - 4.54).abs() < 0.01);
+        // Reference values from the APCA calculator
+        assert!((Color::rgb(0x88, 0x88, 0x88).apca(&white) - 63.06).abs() < 0.01);
+        assert!((white.apca(&Color::rgb(0x88, 0x88, 0x88)) + 68.54).abs() < 0.01);
+        assert_eq!(white.apca(&white), 0.0);
+    }
+
+    #[test]
+    fn delta_e2000() {
+        // Test data from Sharma, Wu and Dalal (2005)
+        let pairs = [
+            ((50.0, 2.6772, -79.7751), (50.0, 0.0, -82.7485), 2.0425),
+            ((50.0, -1.3802, -84.2814), (50.0, 0.0, -82.7485), 1.0),
+            ((50.0, 2.5, 0.0), (73.0, 25.0, -18.0), 27.1492),
+            (
+                (60.2574, -34.0099, 36.2677),
+                (60.4626, -34.1751, 39.4387),
+                1.2644,
+            ),
+            (
+                (22.7233, 20.0904, -46.694),
+                (23.0331, 14.973, -42.5619),
+                2.0373,
+            ),
+        ];
+        for &((l1, a1, b1), (l2, a2, b2), expected) in &pairs {
+            let d = Lab {
+                l: l1,
+                a: a1,
+                b: b1,
+            }
+            .delta_e2000(&Lab {
+                l: l2,
+                a: a2,
+                b: b2,
+            });
+            assert!((d - expected).abs() < 1e-4, "{} != {}", d, expected);
+        }
+    }
+}
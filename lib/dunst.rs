@@ -0,0 +1,170 @@
+use super::{run, splice_file, ui_roles, Applied, Color, Error, GetResult, Getter, State, Theme};
+use config::map::Map;
+use config::Section;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+use template::Format;
+
+const AVAILABLE_FIELDS: &[&str] = &[
+    "output",
+    "splice",
+    "reload",
+    "background",
+    "foreground",
+    "surface",
+    "inactive",
+    "accent",
+    "urgent",
+];
+
+/// Urgency sections with their background, foreground and frame roles.
+const URGENCIES: &[(&str, [&str; 3])] = &[
+    ("urgency_low", ["background", "inactive", "surface"]),
+    ("urgency_normal", ["background", "foreground", "accent"]),
+    ("urgency_critical", ["background", "foreground", "urgent"]),
+];
+
+/// Urgency sections for dunst, written to a drop-in file of `dunstrc.d` or spliced
+/// into `dunstrc`.
+#[derive(Default, Debug)]
+pub struct Dunst {
+    output: Option<PathBuf>,
+    /// Existing config in which the sections are spliced
+    splice: Option<PathBuf>,
+    /// Reload the running daemon once written
+    reload: bool,
+    roles: Map<Color>,
+}
+
+impl Dunst {
+    pub fn new() -> Self {
+        Dunst::default()
+    }
+}
+
+impl Theme for Dunst {
+    fn name(&self) -> &str {
+        "dunst"
+    }
+
+    fn available_fields(&self) -> &[&str] {
+        AVAILABLE_FIELDS
+    }
+
+    fn create(&mut self, state: &State, section: &Section) -> Result<(), Error> {
+        self.output = section.get_path(state, "output").to_option();
+        self.splice = section.get_path(state, "splice").to_option();
+        if let GetResult::Ok(reload) = section.get_bool(state, "reload") {
+            self.reload = reload;
+        }
+        self.roles = ui_roles(state, section)?;
+        Ok(())
+    }
+
+    fn generated(&self) -> Result<String, Error> {
+        let mut buf = Vec::new();
+        for (urgency, roles) in URGENCIES {
+            if !buf.is_empty() {
+                buf.push(String::new());
+            }
+            buf.push(format!("[{}]", urgency));
+            for (key, role) in ["background", "foreground", "frame_color"]
+                .iter()
+                .zip(roles)
+            {
+                if let Some(c) = self.roles.get(role) {
+                    buf.push(format!(
+                        "    {} = \"{}\"",
+                        key,
+                        Format::HexAlpha.translucent(c)
+                    ));
+                }
+            }
+        }
+        Ok(buf.join("\n") + "\n")
+    }
+
+    fn apply(&self) -> Result<Applied, Error> {
+        let generated = self.generated()?;
+        let path = match self.splice {
+            Some(ref path) => {
+                splice_file(path, &generated, "#")?;
+                path.to_owned()
+            }
+            None => {
+                let path = self
+                    .output
+                    .clone()
+                    .unwrap_or_else(|| ::expand_home("~/.config/dunst/dunstrc.d/90-themer.conf"));
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                File::create(&path)?.write_all(generated.as_bytes())?;
+                path
+            }
+        };
+        if !self.reload {
+            return Ok(Applied {
+                output: path,
+                command: None,
+                status: None,
+            });
+        }
+        let status = run(Command::new("dunstctl").arg("reload"))?;
+        Ok(Applied {
+            command: Some("dunstctl reload".to_owned()),
+            output: path,
+            status: Some(status),
+        })
+    }
+
+    fn output(&mut self) -> Option<&PathBuf> {
+        self.output.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use test_utils::{create, state};
+
+    #[test]
+    fn urgencies() {
+        let state = state(
+            "test",
+            &[
+                ("background", Color::rgb(0x19, 0x1d, 0x26)),
+                ("foreground", Color::rgb(0xd0, 0xce, 0xc3)),
+                ("bright_black", Color::rgb(0x55, 0x55, 0x55)),
+                ("red", Color::rgb(0xea, 0x59, 0x65)),
+            ],
+        );
+        let dunst = create(
+            Dunst::new(),
+            &state,
+            "dunst { accent rgba(255, 204, 102, 0.5) }",
+        )
+        .unwrap();
+        assert_eq!(
+            dunst.generated().unwrap(),
+            "\
+[urgency_low]
+    background = \"#191d26\"
+    foreground = \"#555555\"
+    frame_color = \"#2f3239\"
+
+[urgency_normal]
+    background = \"#191d26\"
+    foreground = \"#d0cec3\"
+    frame_color = \"#ffcc6680\"
+
+[urgency_critical]
+    background = \"#191d26\"
+    foreground = \"#d0cec3\"
+    frame_color = \"#ea5965\"
+"
+        );
+    }
+}
@@ -0,0 +1,308 @@
+use super::{
+    derive_shades, editor_roles, run, slug, Applied, Color, Error, GetResult, Getter, State, Theme,
+};
+use config::map::Map;
+use config::Section;
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::process::Command;
+use template::Format;
+use x11::{mapped_color, ANSI};
+
+/// Roles used on top of `EDITOR_ROLES`.
+const ROLES: &[(&str, &[&str])] = &[("special", &["cyan"]), ("success", &["green"])];
+
+const AVAILABLE_FIELDS: &[&str] = &[
+    "name",
+    "output",
+    "reload",
+    "bg",
+    "fg",
+    "cursor",
+    "surface",
+    "selection",
+    "comment",
+    "keyword",
+    "string",
+    "number",
+    "function",
+    "type",
+    "variable",
+    "operator",
+    "special",
+    "error",
+    "warning",
+    "success",
+];
+
+/// Faces with their foreground role, background role and attributes.
+const FACES: &[(&str, &str, &str, &str)] = &[
+    ("default", "fg", "bg", ""),
+    ("cursor", "", "cursor", ""),
+    ("region", "", "selection", ""),
+    ("highlight", "", "surface", ""),
+    ("hl-line", "", "surface", ""),
+    ("fringe", "", "bg", ""),
+    ("vertical-border", "surface", "", ""),
+    ("mode-line", "fg", "surface", ":box nil"),
+    ("mode-line-inactive", "comment", "bg", ":box nil"),
+    ("minibuffer-prompt", "function", "", ":weight bold"),
+    ("line-number", "comment", "", ""),
+    ("line-number-current-line", "fg", "surface", ""),
+    ("link", "function", "", ":underline t"),
+    ("isearch", "bg", "warning", ""),
+    ("lazy-highlight", "", "selection", ""),
+    ("show-paren-match", "", "selection", ":weight bold"),
+    ("error", "error", "", ":weight bold"),
+    ("warning", "warning", "", ""),
+    ("success", "success", "", ""),
+    ("font-lock-comment-face", "comment", "", ":slant italic"),
+    ("font-lock-comment-delimiter-face", "comment", "", ""),
+    ("font-lock-doc-face", "comment", "", ""),
+    ("font-lock-string-face", "string", "", ""),
+    ("font-lock-keyword-face", "keyword", "", ""),
+    ("font-lock-builtin-face", "special", "", ""),
+    ("font-lock-function-name-face", "function", "", ""),
+    ("font-lock-variable-name-face", "variable", "", ""),
+    ("font-lock-type-face", "type", "", ""),
+    ("font-lock-constant-face", "number", "", ""),
+    ("font-lock-preprocessor-face", "keyword", "", ""),
+    ("font-lock-negation-char-face", "operator", "", ""),
+    ("font-lock-warning-face", "warning", "", ":weight bold"),
+];
+
+/// An Emacs custom theme, `<name>-theme.el`.
+#[derive(Default, Debug)]
+pub struct Emacs {
+    /// Name of the theme, the one of the themer theme by default, made a valid symbol
+    theme_name: String,
+    /// File written, in `custom-theme-directory` by default
+    output: Option<PathBuf>,
+    /// Load the theme in the running Emacs server once written
+    reload: bool,
+    terminal: Vec<Color>,
+    roles: Map<Color>,
+}
+
+impl Emacs {
+    pub fn new() -> Self {
+        Emacs::default()
+    }
+
+    fn path(&self) -> PathBuf {
+        self.output.clone().unwrap_or_else(|| {
+            ::expand_home("~/.emacs.d").join(format!("{}-theme.el", self.theme_name))
+        })
+    }
+
+    fn eval(&self) -> String {
+        let dir = self
+            .path()
+            .parent()
+            .map(|p| p.display().to_string())
+            .unwrap_or_default();
+        format!(
+            "(progn (add-to-list 'custom-theme-load-path {}) \
+             (mapc #'disable-theme custom-enabled-themes) (load-theme '{} t))",
+            string(&dir),
+            self.theme_name
+        )
+    }
+}
+
+/// Elisp string literal, only `\` and `"` are escaped.
+fn string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Whether an Emacs server accepts connections on its default socket.
+fn server_running() -> bool {
+    let socket = match env::var_os("EMACS_SOCKET_NAME") {
+        Some(name) => PathBuf::from(name),
+        None => match env::var_os("XDG_RUNTIME_DIR") {
+            Some(dir) if PathBuf::from(&dir).join("emacs/server").exists() => {
+                PathBuf::from(dir).join("emacs/server")
+            }
+            _ => match fs::metadata("/proc/self") {
+                Ok(metadata) => PathBuf::from(format!("/tmp/emacs{}/server", metadata.uid())),
+                Err(_) => return false,
+            },
+        },
+    };
+    UnixStream::connect(socket).is_ok()
+}
+
+impl Theme for Emacs {
+    fn name(&self) -> &str {
+        "emacs"
+    }
+
+    fn available_fields(&self) -> &[&str] {
+        AVAILABLE_FIELDS
+    }
+
+    fn create(&mut self, state: &State, section: &Section) -> Result<(), Error> {
+        let name = match section.get_str(state, "name") {
+            GetResult::Ok(name) => name,
+            _ => state.name.to_owned(),
+        };
+        self.theme_name = slug(&name)?;
+        self.output = section.get_path(state, "output").to_option();
+        if let GetResult::Ok(reload) = section.get_bool(state, "reload") {
+            self.reload = reload;
+        }
+        // Normal colors, in the order of `ansi-color-names-vector`.
+        for name in &ANSI[..8] {
+            if let Some(c) = mapped_color(state, section, name) {
+                self.terminal.push(c);
+            }
+        }
+        self.roles = editor_roles(state, section, ROLES)?;
+        derive_shades(&mut self.roles, &[]);
+        Ok(())
+    }
+
+    fn generated(&self) -> Result<String, Error> {
+        let name = &self.theme_name;
+        let mut buf = vec![
+            format!(
+                ";;; {}-theme.el --- Generated by themer -*- lexical-binding: t -*-",
+                name
+            ),
+            String::new(),
+            format!("(deftheme {})", name),
+            String::new(),
+            "(custom-theme-set-faces".to_owned(),
+            format!(" '{}", name),
+        ];
+        for (face, fg, bg, attributes) in FACES {
+            let mut spec = Vec::new();
+            if let Some(c) = self.roles.get(fg) {
+                spec.push(format!(":foreground \"{}\"", Format::Hex.format(c)));
+            }
+            if let Some(c) = self.roles.get(bg) {
+                spec.push(format!(":background \"{}\"", Format::Hex.format(c)));
+            }
+            if spec.is_empty() {
+                continue;
+            }
+            if !attributes.is_empty() {
+                spec.push(attributes.to_string());
+            }
+            buf.push(format!(" '({} ((t ({}))))", face, spec.join(" ")));
+        }
+        let last = buf.len() - 1;
+        buf[last].push(')');
+        // A partial vector would shift the colors, it is only set when complete.
+        if self.terminal.len() == 8 {
+            let colors: Vec<String> = self
+                .terminal
+                .iter()
+                .map(|c| format!("\"{}\"", Format::Hex.format(c)))
+                .collect();
+            buf.push(String::new());
+            buf.push("(custom-theme-set-variables".to_owned());
+            buf.push(format!(" '{}", name));
+            buf.push(format!(
+                " '(ansi-color-names-vector [{}]))",
+                colors.join(" ")
+            ));
+        }
+        buf.push(String::new());
+        buf.push(";;;###autoload".to_owned());
+        buf.push("(when load-file-name".to_owned());
+        buf.push("  (add-to-list 'custom-theme-load-path".to_owned());
+        buf.push("               (file-name-directory load-file-name)))".to_owned());
+        buf.push(String::new());
+        buf.push(format!("(provide-theme '{})", name));
+        buf.push(String::new());
+        buf.push(format!(";;; {}-theme.el ends here", name));
+        Ok(buf.join("\n") + "\n")
+    }
+
+    fn apply(&self) -> Result<Applied, Error> {
+        let generated = self.generated()?;
+        let path = self.path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        File::create(&path)?.write_all(generated.as_bytes())?;
+        if !self.reload || !server_running() {
+            return Ok(Applied {
+                output: path,
+                command: None,
+                status: None,
+            });
+        }
+        let eval = self.eval();
+        let status = run(Command::new("emacsclient").arg("--eval").arg(&eval))?;
+        Ok(Applied {
+            command: Some(format!("emacsclient --eval '{}'", eval)),
+            output: path,
+            status: Some(status),
+        })
+    }
+
+    fn output(&mut self) -> Option<&PathBuf> {
+        self.output.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use test_utils::{create, state};
+
+    #[test]
+    fn theme() {
+        let state = state(
+            "Ayu Mirage",
+            &[
+                ("background", Color::rgb(0x19, 0x1d, 0x26)),
+                ("foreground", Color::rgb(0xd0, 0xce, 0xc3)),
+                ("red", Color::rgb(0xea, 0x59, 0x65)),
+                ("magenta", Color::rgb(0xc9, 0xae, 0xff)),
+                ("bright_black", Color::rgb(0x55, 0x55, 0x55)),
+            ],
+        );
+        let emacs = create(Emacs::new(), &state, "emacs { keyword red }").unwrap();
+        assert!(emacs.path().ends_with(".emacs.d/ayu-mirage-theme.el"));
+        let generated = emacs.generated().unwrap();
+        assert!(generated.contains("(deftheme ayu-mirage)\n"));
+        assert!(generated
+            .contains(" '(default ((t (:foreground \"#d0cec3\" :background \"#191d26\"))))\n"));
+        assert!(generated.contains(" '(region ((t (:background \"#47494d\"))))\n"));
+        assert!(generated.contains(
+            " '(font-lock-comment-face ((t (:foreground \"#555555\" :slant italic))))\n"
+        ));
+        assert!(generated.contains(" '(font-lock-keyword-face ((t (:foreground \"#ea5965\"))))"));
+        assert!(!generated.contains("ansi-color-names-vector"));
+        assert!(generated.contains("(provide-theme 'ayu-mirage)\n"));
+        assert!(emacs.eval().ends_with("(load-theme 'ayu-mirage t))"));
+    }
+
+    #[test]
+    fn name() {
+        let state = state("Ayu Mirage", &[]);
+        let emacs = create(
+            Emacs::new(),
+            &state,
+            "emacs { name \"My Theme\" output \"/tmp/a \\\"b\\/my-theme-theme.el\" }",
+        )
+        .unwrap();
+        assert_eq!(emacs.theme_name, "my-theme");
+        assert!(emacs
+            .eval()
+            .starts_with("(progn (add-to-list 'custom-theme-load-path \"/tmp/a \\\"b\\\\\")"));
+        assert_eq!(
+            create(Emacs::new(), &state, "emacs { name \"***\" }")
+                .unwrap_err()
+                .to_string(),
+            "invalid theme name `***`"
+        );
+    }
+}
@@ -0,0 +1,317 @@
+use super::{file_name, roles, ui_roles, Applied, Color, Error, GetResult, Getter, State, Theme};
+use config::map::Map;
+use config::Section;
+use std::fs;
+use std::path::{Path, PathBuf};
+use template::Format;
+
+const AVAILABLE_FIELDS: &[&str] = &[
+    "name",
+    "output",
+    "settings",
+    "background",
+    "foreground",
+    "surface",
+    "inactive",
+    "accent",
+    "urgent",
+    "warning",
+    "success",
+];
+
+/// Roles used on top of `UI_ROLES`.
+const STATUS_ROLES: &[(&str, &[&str])] = &[
+    ("warning", &["warning", "yellow"]),
+    ("success", &["success", "green"]),
+];
+
+/// Named colors of the GTK themes and the roles they are taken from.
+const GTK_COLORS: &[(&str, &str)] = &[
+    ("theme_bg_color", "background"),
+    ("theme_fg_color", "foreground"),
+    ("theme_base_color", "background"),
+    ("theme_text_color", "foreground"),
+    ("theme_selected_bg_color", "accent"),
+    ("theme_selected_fg_color", "background"),
+    ("theme_unfocused_bg_color", "background"),
+    ("theme_unfocused_fg_color", "foreground"),
+    ("theme_unfocused_base_color", "background"),
+    ("theme_unfocused_text_color", "foreground"),
+    ("theme_unfocused_selected_bg_color", "surface"),
+    ("theme_unfocused_selected_fg_color", "foreground"),
+    ("insensitive_bg_color", "background"),
+    ("insensitive_fg_color", "inactive"),
+    ("insensitive_base_color", "background"),
+    ("borders", "surface"),
+    ("unfocused_borders", "surface"),
+    ("warning_color", "warning"),
+    ("error_color", "urgent"),
+    ("success_color", "success"),
+];
+
+/// Named colors of libadwaita, for GTK 4 applications using it.
+const ADWAITA_COLORS: &[(&str, &str)] = &[
+    ("accent_color", "accent"),
+    ("accent_bg_color", "accent"),
+    ("accent_fg_color", "background"),
+    ("destructive_color", "urgent"),
+    ("destructive_bg_color", "urgent"),
+    ("destructive_fg_color", "background"),
+    ("success_color", "success"),
+    ("warning_color", "warning"),
+    ("error_color", "urgent"),
+    ("window_bg_color", "background"),
+    ("window_fg_color", "foreground"),
+    ("view_bg_color", "background"),
+    ("view_fg_color", "foreground"),
+    ("headerbar_bg_color", "surface"),
+    ("headerbar_fg_color", "foreground"),
+    ("sidebar_bg_color", "surface"),
+    ("sidebar_fg_color", "foreground"),
+    ("card_bg_color", "surface"),
+    ("card_fg_color", "foreground"),
+    ("dialog_bg_color", "surface"),
+    ("dialog_fg_color", "foreground"),
+    ("popover_bg_color", "surface"),
+    ("popover_fg_color", "foreground"),
+];
+
+/// A theme directory overriding the named colors of Adwaita, with `gtk-3.0/gtk.css`
+/// and `gtk-4.0/gtk.css`.
+#[derive(Default, Debug)]
+pub struct Gtk {
+    /// Name of the GTK theme, the one of the themer theme by default
+    theme_name: String,
+    /// Theme directory, `~/.local/share/themes/<theme_name>` by default
+    output: Option<PathBuf>,
+    /// Select the theme in the `settings.ini` of GTK 3 and 4
+    settings: bool,
+    roles: Map<Color>,
+}
+
+impl Gtk {
+    pub fn new() -> Self {
+        Gtk::default()
+    }
+
+    fn dir(&self) -> PathBuf {
+        self.output
+            .clone()
+            .unwrap_or_else(|| ::expand_home("~/.local/share/themes").join(&self.theme_name))
+    }
+
+    fn dark(&self) -> bool {
+        match self.roles.get("background") {
+            Some(bg) => bg.contrast(&Color::rgb(255, 255, 255)) > bg.contrast(&Color::rgb(0, 0, 0)),
+            None => false,
+        }
+    }
+
+    fn define_colors(&self, buf: &mut Vec<String>, colors: &[(&str, &str)]) {
+        for (name, role) in colors {
+            if let Some(c) = self.roles.get(role) {
+                buf.push(format!("@define-color {} {};", name, Format::Hex.format(c)));
+            }
+        }
+    }
+
+    fn gtk4(&self) -> String {
+        let mut buf = vec!["/* Generated by themer */".to_owned()];
+        self.define_colors(&mut buf, GTK_COLORS);
+        buf.push(String::new());
+        self.define_colors(&mut buf, ADWAITA_COLORS);
+        buf.join("\n") + "\n"
+    }
+}
+
+/// Set `key` in the `[section]` of an ini file, adding either if it is missing.
+fn set_ini(content: &str, section: &str, key: &str, value: &str) -> String {
+    let header = format!("[{}]", section);
+    let line = format!("{}={}", key, value);
+    let mut lines: Vec<String> = content.lines().map(|l| l.to_owned()).collect();
+    match lines.iter().position(|l| l.trim() == header) {
+        Some(start) => {
+            let end = lines[start + 1..]
+                .iter()
+                .position(|l| l.trim_start().starts_with('['))
+                .map(|i| start + 1 + i)
+                .unwrap_or_else(|| lines.len());
+            let existing = lines[start + 1..end]
+                .iter()
+                .position(|l| l.split('=').next().map(|k| k.trim()) == Some(key));
+            match existing {
+                Some(i) => lines[start + 1 + i] = line,
+                None => lines.insert(start + 1, line),
+            }
+        }
+        None => {
+            if lines.last().map(|l| !l.trim().is_empty()).unwrap_or(false) {
+                lines.push(String::new());
+            }
+            lines.push(header);
+            lines.push(line);
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+fn select_theme(settings: &Path, name: &str) -> Result<(), Error> {
+    let content = match fs::read_to_string(settings) {
+        Ok(content) => content,
+        Err(ref e) if e.kind() == ::std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    if let Some(parent) = settings.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(
+        settings,
+        set_ini(&content, "Settings", "gtk-theme-name", name),
+    )?;
+    Ok(())
+}
+
+impl Theme for Gtk {
+    fn name(&self) -> &str {
+        "gtk"
+    }
+
+    fn available_fields(&self) -> &[&str] {
+        AVAILABLE_FIELDS
+    }
+
+    fn create(&mut self, state: &State, section: &Section) -> Result<(), Error> {
+        self.theme_name = match section.get_str(state, "name") {
+            GetResult::Ok(name) => name,
+            _ => state.name.to_owned(),
+        };
+        file_name(&self.theme_name)?;
+        self.output = section.get_path(state, "output").to_option();
+        if let GetResult::Ok(settings) = section.get_bool(state, "settings") {
+            self.settings = settings;
+        }
+        self.roles = ui_roles(state, section)?;
+        for entry in &roles(state, section, STATUS_ROLES)? {
+            self.roles
+                .insert(entry.name.to_owned(), entry.value.clone());
+        }
+        Ok(())
+    }
+
+    /// The GTK 3 stylesheet, which extends Adwaita.
+    fn generated(&self) -> Result<String, Error> {
+        let variant = if self.dark() { "-dark" } else { "" };
+        let mut buf = vec![
+            "/* Generated by themer */".to_owned(),
+            format!(
+                "@import url(\"resource:///org/gtk/libgtk/theme/Adwaita/gtk-contained{}.css\");",
+                variant
+            ),
+            String::new(),
+        ];
+        self.define_colors(&mut buf, GTK_COLORS);
+        Ok(buf.join("\n") + "\n")
+    }
+
+    fn apply(&self) -> Result<Applied, Error> {
+        let dir = self.dir();
+        fs::create_dir_all(dir.join("gtk-3.0"))?;
+        fs::create_dir_all(dir.join("gtk-4.0"))?;
+        fs::write(dir.join("gtk-3.0/gtk.css"), self.generated()?)?;
+        fs::write(dir.join("gtk-4.0/gtk.css"), self.gtk4())?;
+        fs::write(
+            dir.join("index.theme"),
+            format!(
+                "[Desktop Entry]\nType=X-GNOME-Metatheme\nName={0}\n\n[X-GNOME-Metatheme]\nGtkTheme={0}\n",
+                self.theme_name
+            ),
+        )?;
+        if self.settings {
+            for version in &["gtk-3.0", "gtk-4.0"] {
+                let settings = ::expand_home("~/.config")
+                    .join(version)
+                    .join("settings.ini");
+                select_theme(&settings, &self.theme_name)?;
+            }
+        }
+        Ok(Applied {
+            output: dir,
+            command: None,
+            status: None,
+        })
+    }
+
+    fn output(&mut self) -> Option<&PathBuf> {
+        self.output.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use test_utils::{create, state};
+
+    #[test]
+    fn stylesheets() {
+        let state = state(
+            "ayu",
+            &[
+                ("background", Color::rgb(0x19, 0x1d, 0x26)),
+                ("foreground", Color::rgb(0xd0, 0xce, 0xc3)),
+                ("blue", Color::rgb(0x2d, 0x91, 0xd0)),
+            ],
+        );
+        let gtk = create(Gtk::new(), &state, "gtk { success #00ff00 }").unwrap();
+        assert!(gtk.dir().ends_with(".local/share/themes/ayu"));
+        let gtk3 = gtk.generated().unwrap();
+        assert!(gtk3.contains("Adwaita/gtk-contained-dark.css"));
+        assert!(gtk3.contains("@define-color theme_selected_bg_color #2d91d0;\n"));
+        assert!(gtk3.contains("@define-color success_color #00ff00;\n"));
+        assert!(!gtk3.contains("warning_color"));
+        assert!(gtk
+            .gtk4()
+            .contains("@define-color window_bg_color #191d26;\n"));
+    }
+
+    #[test]
+    fn name() {
+        let ayu = state("ayu", &[]);
+        let gtk = create(Gtk::new(), &ayu, "gtk { name \"Ayu Mirage\" }").unwrap();
+        assert!(gtk.dir().ends_with(".local/share/themes/Ayu Mirage"));
+        assert_eq!(
+            create(Gtk::new(), &ayu, "gtk { name \"ayu/../../x\" }")
+                .unwrap_err()
+                .to_string(),
+            "invalid theme name `ayu/../../x`"
+        );
+        for name in &["", "..", "../ayu"] {
+            assert_eq!(
+                create(Gtk::new(), &state(name, &[]), "gtk {}")
+                    .unwrap_err()
+                    .to_string(),
+                format!("invalid theme name `{}`", name)
+            );
+        }
+    }
+
+    #[test]
+    fn settings_ini() {
+        assert_eq!(
+            set_ini("", "Settings", "gtk-theme-name", "ayu"),
+            "[Settings]\ngtk-theme-name=ayu\n"
+        );
+        assert_eq!(
+            set_ini(
+                "[Settings]\ngtk-theme-name = Adwaita\ngtk-font-name=Sans 10\n",
+                "Settings",
+                "gtk-theme-name",
+                "ayu"
+            ),
+            "[Settings]\ngtk-theme-name=ayu\ngtk-font-name=Sans 10\n"
+        );
+        assert_eq!(
+            set_ini("[Other]\na=1\n", "Settings", "gtk-theme-name", "ayu"),
+            "[Other]\na=1\n\n[Settings]\ngtk-theme-name=ayu\n"
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use super::{run, splice_file, ui_roles, Applied, Color, Error, GetResult, Getter, State, Theme};
+use config::map::Map;
+use config::Section;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+use template::Format;
+
+const AVAILABLE_FIELDS: &[&str] = &[
+    "output",
+    "splice",
+    "bar",
+    "reload",
+    "background",
+    "foreground",
+    "surface",
+    "inactive",
+    "accent",
+    "urgent",
+];
+
+/// Window classes with their border, background, text, indicator and child border.
+const CLIENTS: &[(&str, [&str; 5])] = &[
+    (
+        "focused",
+        ["accent", "accent", "background", "accent", "accent"],
+    ),
+    (
+        "focused_inactive",
+        ["surface", "surface", "foreground", "surface", "surface"],
+    ),
+    (
+        "unfocused",
+        [
+            "background",
+            "background",
+            "inactive",
+            "background",
+            "background",
+        ],
+    ),
+    (
+        "urgent",
+        ["urgent", "urgent", "background", "urgent", "urgent"],
+    ),
+    (
+        "placeholder",
+        [
+            "background",
+            "background",
+            "foreground",
+            "background",
+            "background",
+        ],
+    ),
+];
+
+/// Workspace buttons of the bar with their border, background and text.
+const WORKSPACES: &[(&str, [&str; 3])] = &[
+    ("focused_workspace", ["accent", "accent", "background"]),
+    ("active_workspace", ["surface", "surface", "foreground"]),
+    (
+        "inactive_workspace",
+        ["background", "background", "inactive"],
+    ),
+    ("urgent_workspace", ["urgent", "urgent", "background"]),
+    ("binding_mode", ["urgent", "urgent", "background"]),
+];
+
+/// Window colors for i3 or sway, written to a file to `include` or spliced into
+/// the main config.
+#[derive(Debug)]
+pub struct I3 {
+    /// `i3` or `sway`
+    program: String,
+    output: Option<PathBuf>,
+    /// Existing config in which the colors are spliced
+    splice: Option<PathBuf>,
+    /// Add a `bar` block, off by default as it declares a new bar next to the ones of
+    /// the config
+    bar: bool,
+    /// Reload the window manager once written
+    reload: bool,
+    roles: Map<Color>,
+}
+
+impl I3 {
+    pub fn new(program: &str) -> Self {
+        I3 {
+            program: program.to_owned(),
+            output: None,
+            splice: None,
+            bar: false,
+            reload: false,
+            roles: Map::new(),
+        }
+    }
+
+    fn reload_command(&self) -> &str {
+        if self.program == "sway" {
+            "swaymsg"
+        } else {
+            "i3-msg"
+        }
+    }
+}
+
+impl Theme for I3 {
+    fn name(&self) -> &str {
+        &self.program
+    }
+
+    fn available_fields(&self) -> &[&str] {
+        AVAILABLE_FIELDS
+    }
+
+    fn create(&mut self, state: &State, section: &Section) -> Result<(), Error> {
+        self.output = section.get_path(state, "output").to_option();
+        self.splice = section.get_path(state, "splice").to_option();
+        if let GetResult::Ok(bar) = section.get_bool(state, "bar") {
+            self.bar = bar;
+        }
+        if let GetResult::Ok(reload) = section.get_bool(state, "reload") {
+            self.reload = reload;
+        }
+        self.roles = ui_roles(state, section)?;
+        Ok(())
+    }
+
+    fn generated(&self) -> Result<String, Error> {
+        // Lines missing a color are left out, the window manager keeps its default.
+        let colors = |names: &[&str]| -> Option<Vec<String>> {
+            names
+                .iter()
+                .map(|n| self.roles.get(n).map(|c| Format::Hex.format(c)))
+                .collect()
+        };
+        let mut buf = Vec::new();
+        for (class, names) in CLIENTS {
+            if let Some(colors) = colors(names) {
+                buf.push(format!("client.{:<17}{}", class, colors.join(" ")));
+            }
+        }
+        if self.program != "sway" {
+            if let Some(bg) = self.roles.get("background") {
+                buf.push(format!(
+                    "client.{:<17}{}",
+                    "background",
+                    Format::Hex.format(bg)
+                ));
+            }
+        }
+        if self.bar {
+            buf.push(String::new());
+            buf.push("bar {".to_owned());
+            buf.push("    colors {".to_owned());
+            for (key, name) in &[
+                ("background", "background"),
+                ("statusline", "foreground"),
+                ("separator", "inactive"),
+            ] {
+                if let Some(c) = self.roles.get(name) {
+                    buf.push(format!("        {:<19}{}", key, Format::Hex.format(c)));
+                }
+            }
+            for (key, names) in WORKSPACES {
+                if let Some(colors) = colors(names) {
+                    buf.push(format!("        {:<19}{}", key, colors.join(" ")));
+                }
+            }
+            buf.push("    }".to_owned());
+            buf.push("}".to_owned());
+        }
+        Ok(buf.join("\n") + "\n")
+    }
+
+    fn apply(&self) -> Result<Applied, Error> {
+        let generated = self.generated()?;
+        let path = match self.splice {
+            Some(ref path) => {
+                splice_file(path, &generated, "#")?;
+                path.to_owned()
+            }
+            None => {
+                let path = self.output.clone().unwrap_or_else(|| {
+                    ::expand_home(&format!("~/.config/{}/themer", self.program))
+                });
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                File::create(&path)?.write_all(generated.as_bytes())?;
+                path
+            }
+        };
+        if !self.reload {
+            return Ok(Applied {
+                output: path,
+                command: None,
+                status: None,
+            });
+        }
+        let status = run(Command::new(self.reload_command()).arg("reload"))?;
+        Ok(Applied {
+            command: Some(format!("{} reload", self.reload_command())),
+            output: path,
+            status: Some(status),
+        })
+    }
+
+    fn output(&mut self) -> Option<&PathBuf> {
+        self.output.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use test_utils::{create, state};
+
+    fn wm(program: &str, section: &str) -> Result<I3, Error> {
+        let state = state(
+            "test",
+            &[
+                ("background", Color::rgb(0x19, 0x1d, 0x26)),
+                ("foreground", Color::rgb(0xd0, 0xce, 0xc3)),
+                ("red", Color::rgb(0xea, 0x59, 0x65)),
+                ("blue", Color::rgb(0x2d, 0x91, 0xd0)),
+                ("bright_black", Color::rgb(0x55, 0x55, 0x55)),
+            ],
+        );
+        create(I3::new(program), &state, section)
+    }
+
+    #[test]
+    fn i3() {
+        assert_eq!(
+            wm("i3", "i3 { accent magenta }").unwrap_err().to_string(),
+            "unknown color `magenta` used by `accent`"
+        );
+        assert_eq!(
+            wm("i3", "i3 { accent #ff00ff }")
+                .unwrap()
+                .generated()
+                .unwrap(),
+            "\
+client.focused          #ff00ff #ff00ff #191d26 #ff00ff #ff00ff
+client.focused_inactive #2f3239 #2f3239 #d0cec3 #2f3239 #2f3239
+client.unfocused        #191d26 #191d26 #555555 #191d26 #191d26
+client.urgent           #ea5965 #ea5965 #191d26 #ea5965 #ea5965
+client.placeholder      #191d26 #191d26 #d0cec3 #191d26 #191d26
+client.background       #191d26
+"
+        );
+    }
+
+    #[test]
+    fn sway_bar() {
+        let generated = wm("sway", "sway { bar yes }").unwrap().generated().unwrap();
+        assert!(!generated.contains("client.background"));
+        assert!(generated.ends_with(
+            "\
+bar {
+    colors {
+        background         #191d26
+        statusline         #d0cec3
+        separator          #555555
+        focused_workspace  #2d91d0 #2d91d0 #191d26
+        active_workspace   #2f3239 #2f3239 #d0cec3
+        inactive_workspace #191d26 #191d26 #555555
+        urgent_workspace   #ea5965 #ea5965 #191d26
+        binding_mode       #ea5965 #ea5965 #191d26
+    }
+}
+"
+        ));
+    }
+}
@@ -0,0 +1,228 @@
+use super::{run, Applied, Color, Error, GetResult, Getter, State, Theme};
+use config::Section;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+use template::Format;
+use x11::{mapped_color, ANSI};
+
+const AVAILABLE_FIELDS: &[&str] = &[
+    "output",
+    "socket",
+    "reload",
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright_black",
+    "bright_red",
+    "bright_green",
+    "bright_yellow",
+    "bright_blue",
+    "bright_magenta",
+    "bright_cyan",
+    "bright_white",
+    "foreground",
+    "background",
+    "cursor",
+    "cursor_text",
+    "selection_foreground",
+    "selection_background",
+    "active_tab_foreground",
+    "active_tab_background",
+    "inactive_tab_foreground",
+    "inactive_tab_background",
+    "tab_bar_background",
+];
+
+/// Kitty settings and the colors they are taken from, the first one defined is used.
+const KEYS: &[(&str, &[&str])] = &[
+    ("foreground", &["foreground"]),
+    ("background", &["background"]),
+    ("cursor", &["cursor"]),
+    ("cursor_text_color", &["cursor_text"]),
+    ("selection_foreground", &["selection_foreground"]),
+    ("selection_background", &["selection_background"]),
+    (
+        "active_tab_foreground",
+        &["active_tab_foreground", "background"],
+    ),
+    (
+        "active_tab_background",
+        &["active_tab_background", "foreground"],
+    ),
+    (
+        "inactive_tab_foreground",
+        &["inactive_tab_foreground", "foreground"],
+    ),
+    (
+        "inactive_tab_background",
+        &["inactive_tab_background", "bright_black"],
+    ),
+    ("tab_bar_background", &["tab_bar_background", "background"]),
+];
+
+#[derive(Default, Debug)]
+pub struct Kitty {
+    output: Option<PathBuf>,
+    /// Remote control address, e.g. `unix:/tmp/kitty`
+    socket: Option<String>,
+    /// Push the colors into running instances with `kitty @ set-colors`
+    reload: bool,
+    colors: Vec<(String, Color)>,
+}
+
+impl Kitty {
+    pub fn new() -> Self {
+        Kitty::default()
+    }
+
+    fn path(&self) -> PathBuf {
+        self.output
+            .clone()
+            .unwrap_or_else(|| ::expand_home("~/.config/kitty/theme.conf"))
+    }
+
+    /// Arguments of the remote control command recoloring running instances.
+    fn reload_args(&self) -> Vec<String> {
+        let mut args = vec!["@".to_owned()];
+        if let Some(ref socket) = self.socket {
+            args.push("--to".to_owned());
+            args.push(socket.to_owned());
+        }
+        args.push("set-colors".to_owned());
+        args.push("--all".to_owned());
+        args.push("--configured".to_owned());
+        args.push(self.path().display().to_string());
+        args
+    }
+}
+
+impl Theme for Kitty {
+    fn name(&self) -> &str {
+        "kitty"
+    }
+
+    fn available_fields(&self) -> &[&str] {
+        AVAILABLE_FIELDS
+    }
+
+    fn create(&mut self, state: &State, section: &Section) -> Result<(), Error> {
+        self.output = section.get_path(state, "output").to_option();
+        if let GetResult::Ok(socket) = section.get_str(state, "socket") {
+            self.socket = Some(socket);
+        }
+        // Without a socket, remote control only works from inside kitty.
+        self.reload = section
+            .get_bool(state, "reload")
+            .to_option()
+            .unwrap_or_else(|| {
+                self.socket.is_some() || ::std::env::var_os("KITTY_LISTEN_ON").is_some()
+            });
+        for (key, names) in KEYS {
+            if let Some(c) = names
+                .iter()
+                .filter_map(|name| mapped_color(state, section, name))
+                .next()
+            {
+                self.colors.push((key.to_string(), c));
+            }
+        }
+        for (i, name) in ANSI.iter().enumerate() {
+            if let Some(c) = mapped_color(state, section, name) {
+                self.colors.push((format!("color{}", i), c));
+            }
+        }
+        Ok(())
+    }
+
+    fn generated(&self) -> Result<String, Error> {
+        let mut buf = Vec::new();
+        for (key, c) in &self.colors {
+            buf.push(format!("{:<24}{}", key, Format::Hex.format(c)));
+        }
+        Ok(buf.join("\n") + "\n")
+    }
+
+    fn apply(&self) -> Result<Applied, Error> {
+        let generated = self.generated()?;
+        let path = self.path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        File::create(&path)?.write_all(generated.as_bytes())?;
+        if !self.reload {
+            return Ok(Applied {
+                output: path,
+                command: None,
+                status: None,
+            });
+        }
+        let args = self.reload_args();
+        let status = run(Command::new("kitty").args(&args))?;
+        Ok(Applied {
+            command: Some(format!("kitty {}", args.join(" "))),
+            output: path,
+            status: Some(status),
+        })
+    }
+
+    fn output(&mut self) -> Option<&PathBuf> {
+        self.output.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use test_utils::{create, state};
+
+    fn kitty(section: &str) -> Kitty {
+        let state = state(
+            "test",
+            &[
+                ("background", Color::rgb(0x19, 0x1d, 0x26)),
+                ("foreground", Color::rgb(0xd0, 0xce, 0xc3)),
+                ("red", Color::rgb(0xea, 0x59, 0x65)),
+                ("bright_black", Color::rgb(0x55, 0x55, 0x55)),
+            ],
+        );
+        create(Kitty::new(), &state, section).unwrap()
+    }
+
+    #[test]
+    fn snapshot() {
+        let kitty = kitty("kitty { reload no color15 #ffffff }");
+        assert!(!kitty.reload);
+        assert_eq!(
+            kitty.generated().unwrap(),
+            "\
+foreground              #d0cec3
+background              #191d26
+active_tab_foreground   #191d26
+active_tab_background   #d0cec3
+inactive_tab_foreground #d0cec3
+inactive_tab_background #555555
+tab_bar_background      #191d26
+color1                  #ea5965
+color8                  #555555
+color15                 #ffffff
+"
+        );
+    }
+
+    #[test]
+    fn reload() {
+        let kitty = kitty("kitty { socket \"unix:/tmp/kitty\" output /tmp/theme.conf }");
+        assert!(kitty.reload);
+        assert_eq!(
+            kitty.reload_args().join(" "),
+            "@ --to unix:/tmp/kitty set-colors --all --configured /tmp/theme.conf"
+        );
+    }
+}
@@ -1,55 +1,60 @@
+// `failure_derive` expands into impls nested in a named const.
+#![allow(non_local_definitions)]
+
 extern crate failure;
 #[macro_use]
 extern crate failure_derive;
+#[cfg(test)]
+extern crate proptest;
+#[macro_use]
+extern crate serde_json;
 extern crate themer_config as config;
 
+mod alacritty;
+pub mod color;
+mod dunst;
+mod emacs;
+mod gtk;
+mod i3;
+mod kitty;
+pub mod lint;
+mod osc;
+mod polybar;
+pub mod preview;
+mod qt;
+mod rofi;
 pub mod template;
+mod tmux;
+mod vim;
+mod vscode;
 mod x11;
 
+use alacritty::Alacritty;
+pub use color::Color;
 use config::{map::Map, Config, Section, Value};
+use dunst::Dunst;
+use emacs::Emacs;
+use gtk::Gtk;
+use i3::I3;
+use kitty::Kitty;
+use osc::Osc;
+use polybar::Polybar;
+use qt::{Kde, Qt};
+use rofi::Rofi;
 use std::collections::VecDeque;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use template::{Registry, TemplateTheme};
+use tmux::Tmux;
+use vim::Vim;
+use vscode::Vscode;
 use x11::X11;
 
-#[derive(Clone, Debug)]
-pub struct Color(u8, u8, u8);
-
-//const COLOR_NAMES: &[&str] = &[
-//    "cursor",
-//    "cursor_foreground",
-//    "cursor_background",
-//    "foreground",
-//    "background",
-//    "highlight",
-//    "black",
-//    "red",
-//    "green",
-//    "yellow",
-//    "blue",
-//    "magenta",
-//    "cyan",
-//    "white",
-//    "bright_black",
-//    "bright_red",
-//    "bright_green",
-//    "bright_yellow",
-//    "bright_blue",
-//    "bright_magenta",
-//    "bright_cyan",
-//    "bright_white",
-//];
-//
-//fn is_color_name(name: &str) -> bool {
-//    for color in COLOR_NAMES {
-//        if &name == color {
-//            return true;
-//        }
-//    }
-//    false
-//}
-
 #[derive(Debug)]
 pub struct State {
+    /// Name of the theme being processed
+    pub name: String,
     pub colors: Map<Color>,
     pub defined: Map<Value>,
 }
@@ -58,8 +63,32 @@ pub struct State {
 pub enum Error {
     #[fail(display = "expected color found {:?}", _0)]
     ExpectedColor(Value),
+    #[fail(display = "expected number found {:?}", _0)]
+    ExpectedNumber(Value),
+    #[fail(display = "unknown color `{}` used by `{}`", _0, _1)]
+    UnknownColor(String, String),
+    #[fail(display = "cycle between colors {}", _0)]
+    ColorCycle(String),
+    #[fail(display = "unknown function `{}`", _0)]
+    UnknownFunction(String),
     #[fail(display = "unknown section `{}`", _0)]
     UnknownSection(String),
+    #[fail(display = "theme `{}` not found", _0)]
+    ThemeNotFound(String),
+    #[fail(display = "invalid theme name `{}`", _0)]
+    InvalidName(String),
+    #[fail(display = "theme `{}` has no `{}` color", _0, _1)]
+    MissingColor(String, String),
+    #[fail(display = "{}: {}", _0, _1)]
+    InvalidConfig(String, #[cause] config::Error),
+    #[fail(display = "{}: {}", _0, _1)]
+    InvalidTemplate(String, #[cause] template::Error),
+    #[fail(display = "template `{}`: {}", _0, _1)]
+    MissingInput(String, #[cause] template::MissingInput),
+    #[fail(display = "could not run `{}`: {}", _0, _1)]
+    Command(String, #[cause] ::std::io::Error),
+    #[fail(display = "{}: {}", _0, _1)]
+    Section(String, Box<Error>),
     #[fail(display = "io error: {}", _0)]
     Io(#[cause] ::std::io::Error),
 }
@@ -70,12 +99,37 @@ impl From<::std::io::Error> for Error {
     }
 }
 
+/// What a theme did when it was applied.
+#[derive(Debug)]
+pub struct Applied {
+    pub output: PathBuf,
+    pub command: Option<String>,
+    pub status: Option<ExitStatus>,
+}
+
+impl Applied {
+    pub fn success(&self) -> bool {
+        self.status.map(|s| s.success()).unwrap_or(true)
+    }
+}
+
+/// Run a command, a failure to start it is reported with its name.
+pub(crate) fn run(command: &mut Command) -> Result<ExitStatus, Error> {
+    command
+        .status()
+        .map_err(|e| Error::Command(command.get_program().to_string_lossy().into_owned(), e))
+}
+
+/// Theme of a section, or the error which prevented its creation.
+pub type SectionTheme = Result<Box<dyn Theme>, Error>;
+
 // TODO: better name
 pub trait Theme: ::std::fmt::Debug {
+    fn name(&self) -> &str;
     fn available_fields(&self) -> &[&str];
-    fn create(&mut self, &State, &Section) -> Result<(), Error>;
+    fn create(&mut self, state: &State, section: &Section) -> Result<(), Error>;
     fn generated(&self) -> Result<String, Error>;
-    fn apply(&self) -> Result<(), Error>;
+    fn apply(&self) -> Result<Applied, Error>;
     fn output(&mut self) -> Option<&PathBuf>;
 }
 
@@ -109,7 +163,7 @@ pub trait Getter {
 
     fn get_str(&self, state: &State, name: &str) -> GetResult<String> {
         match self.get(name).or(state.defined.get(name)) {
-            Some(&Value::Str(ref s)) => GetResult::Ok(s.to_owned()),
+            Some(Value::Str(s)) => GetResult::Ok(s.to_owned()),
             Some(_) => GetResult::Expected("string".to_string()),
             None => GetResult::NotFound,
         }
@@ -133,9 +187,22 @@ pub trait Getter {
         }
     }
 
+    /// `true`, `yes` or `on`, and `false`, `no` or `off`.
+    fn get_bool(&self, state: &State, name: &str) -> GetResult<bool> {
+        match self.get(name).or(state.defined.get(name)) {
+            Some(Value::Str(s)) => match s.as_str() {
+                "true" | "yes" | "on" => GetResult::Ok(true),
+                "false" | "no" | "off" => GetResult::Ok(false),
+                _ => GetResult::Expected("boolean".to_string()),
+            },
+            Some(_) => GetResult::Expected("boolean".to_string()),
+            None => GetResult::NotFound,
+        }
+    }
+
     fn get_path(&self, state: &State, name: &str) -> GetResult<PathBuf> {
         match self.get(name).or(state.defined.get(name)) {
-            Some(&Value::Path(ref s)) => GetResult::Ok(PathBuf::from(s)),
+            Some(Value::Path(s)) => GetResult::Ok(expand_home(s)),
             Some(_) => GetResult::Expected("path".to_string()),
             None => GetResult::NotFound,
         }
@@ -150,24 +217,255 @@ impl Getter for Section {
 
 fn expect_color(value: &Value) -> Result<Color, Error> {
     match value {
-        Value::Hex(h) => Ok(Color(
+        Value::Hex(h) => Ok(Color::rgb(
             ((h & 0xff0000) >> 16) as u8,
             ((h & 0x00ff00) >> 8) as u8,
             (h & 0x0000ff) as u8,
         )),
-        Value::RGB(r, g, b) => Ok(Color(*r, *g, *b)),
+        Value::RGB(r, g, b) => Ok(Color::rgb(*r, *g, *b)),
+        Value::RGBA(r, g, b, a) => Ok(Color::rgba(*r, *g, *b, *a)),
         v => Err(Error::ExpectedColor(v.clone())),
     }
 }
 
+fn expect_number(value: &Value) -> Result<f64, Error> {
+    match value {
+        Value::Number(n) => Ok(f64::from(*n)),
+        Value::Float(f) => Ok(*f),
+        v => Err(Error::ExpectedNumber(v.clone())),
+    }
+}
+
+/// Percent-like argument, written either between -1 and 1 or in percent: `20`,
+/// `20%` and `0.2` are the same.
+fn fraction(value: &Value) -> Result<f64, Error> {
+    let f = expect_number(value)?;
+    Ok(if f.abs() > 1.0 { f / 100.0 } else { f })
+}
+
+/// Evaluate a color value, `Ok(None)` if it uses a color that is not resolved yet.
+fn eval_color(state: &State, value: &Value) -> Result<Option<Color>, Error> {
+    match value {
+        Value::Str(name) => Ok(state.colors.get(name).cloned()),
+        Value::Call(function, args) => eval_call(state, function, args),
+        v => expect_color(v).map(Some),
+    }
+}
+
+fn eval_call(state: &State, function: &str, args: &[Value]) -> Result<Option<Color>, Error> {
+    let percent = |i: usize| fraction(&args[i]);
+    let color = match function {
+        "hsl" => Color::from_hsl(expect_number(&args[0])?, percent(1)?, percent(2)?),
+        "hsv" => Color::from_hsv(expect_number(&args[0])?, percent(1)?, percent(2)?),
+        _ => {
+            let c = match eval_color(state, &args[0])? {
+                Some(c) => c,
+                None => return Ok(None),
+            };
+            match function {
+                "lighten" => c.lighten(percent(1)?),
+                "darken" => c.darken(percent(1)?),
+                "mix" => match eval_color(state, &args[1])? {
+                    Some(other) => c.mix(&other, percent(2)?),
+                    None => return Ok(None),
+                },
+                "shade" => {
+                    let t = percent(1)?;
+                    if t >= 0.0 {
+                        c.mix(&Color::rgba(0, 0, 0, c.alpha()), t)
+                    } else {
+                        c.mix(&Color::rgba(255, 255, 255, c.alpha()), -t)
+                    }
+                }
+                f => return Err(Error::UnknownFunction(f.to_owned())),
+            }
+        }
+    };
+    Ok(Some(color))
+}
+
+/// Colors referenced by a value.
+fn references(value: &Value) -> Vec<&str> {
+    match value {
+        Value::Str(name) => vec![name],
+        Value::Call(_, args) => args.iter().flat_map(references).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Colors of semantic roles, e.g. `accent`. Each role is either given by the section,
+/// where it can use palette colors and functions, or is the first of its defaults
+/// found in the palette. Roles without a color are left out.
+fn roles(state: &State, section: &Section, roles: &[(&str, &[&str])]) -> Result<Map<Color>, Error> {
+    let mut colors = Map::new();
+    for (role, defaults) in roles {
+        let color = match section.get(role) {
+            Some(value) => match eval_color(state, value)? {
+                Some(c) => Some(c),
+                None => {
+                    let missing = references(value)
+                        .into_iter()
+                        .find(|name| state.colors.get(name).is_none())
+                        .unwrap_or_default();
+                    return Err(Error::UnknownColor(missing.to_owned(), role.to_string()));
+                }
+            },
+            None => defaults
+                .iter()
+                .filter_map(|name| x11::mapped_color(state, section, name))
+                .next(),
+        };
+        if let Some(c) = color {
+            colors.insert(role.to_string(), c);
+        }
+    }
+    Ok(colors)
+}
+
+/// Roles shared by the window manager, bar, launcher and notification targets.
+const UI_ROLES: &[(&str, &[&str])] = &[
+    ("background", &["background"]),
+    ("foreground", &["foreground"]),
+    ("surface", &["surface"]),
+    ("inactive", &["bright_black"]),
+    ("accent", &["accent", "blue"]),
+    ("urgent", &["urgent", "red"]),
+];
+
+/// Roles shared by the editor targets, which add their own on top of them.
+const EDITOR_ROLES: &[(&str, &[&str])] = &[
+    ("bg", &["background"]),
+    ("fg", &["foreground"]),
+    ("cursor", &["cursor", "foreground"]),
+    ("surface", &["surface"]),
+    ("selection", &["selection_background"]),
+    ("comment", &["comment", "bright_black"]),
+    ("keyword", &["magenta"]),
+    ("string", &["green"]),
+    ("number", &["yellow"]),
+    ("function", &["blue"]),
+    ("type", &["yellow"]),
+    ("variable", &["foreground"]),
+    ("operator", &["cyan"]),
+    ("error", &["red"]),
+    ("warning", &["yellow"]),
+];
+
+/// Shades of the editor targets, as the amount of `fg` mixed into `bg`.
+const SHADES: &[(&str, f64)] = &[("surface", 0.12), ("selection", 0.25)];
+
+/// `EDITOR_ROLES` and the `extra` roles of a target.
+fn editor_roles(
+    state: &State,
+    section: &Section,
+    extra: &[(&str, &[&str])],
+) -> Result<Map<Color>, Error> {
+    let mut colors = roles(state, section, EDITOR_ROLES)?;
+    for entry in &roles(state, section, extra)? {
+        colors.insert(entry.name.to_owned(), entry.value.clone());
+    }
+    Ok(colors)
+}
+
+/// Derive the `SHADES` and `extra` shades the theme does not have from `bg` and `fg`.
+fn derive_shades(colors: &mut Map<Color>, extra: &[(&str, f64)]) {
+    if let (Some(bg), Some(fg)) = (colors.get("bg").cloned(), colors.get("fg").cloned()) {
+        for (role, amount) in SHADES.iter().chain(extra) {
+            if colors.get(role).is_none() {
+                colors.insert(role.to_string(), bg.mix(&fg, *amount));
+            }
+        }
+    }
+}
+
+/// `UI_ROLES`, with a `surface` between the background and the foreground if the
+/// theme does not have one.
+fn ui_roles(state: &State, section: &Section) -> Result<Map<Color>, Error> {
+    let mut colors = roles(state, section, UI_ROLES)?;
+    if colors.get("surface").is_none() {
+        if let (Some(bg), Some(fg)) = (colors.get("background"), colors.get("foreground")) {
+            let surface = bg.mix(fg, 0.12);
+            colors.insert("surface".to_owned(), surface);
+        }
+    }
+    Ok(colors)
+}
+
+fn add_color<'a>(
+    state: &mut State,
+    to_resolve: &mut VecDeque<(String, &'a Value)>,
+    name: String,
+    value: &'a Value,
+) -> Result<(), Error> {
+    match value {
+        Value::Str(_) | Value::Call(..) => to_resolve.push_back((name, value)),
+        Value::Hex(_) | Value::RGB(..) | Value::RGBA(..) => {
+            let color = expect_color(value)?;
+            state.colors.insert(name, color);
+        }
+        // Other values, e.g. paths, are not colors and are skipped.
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Resolve references and functions until all colors are known.
+fn resolve_colors(
+    state: &mut State,
+    mut to_resolve: VecDeque<(String, &Value)>,
+) -> Result<(), Error> {
+    let mut has_resolved = true;
+    while has_resolved && !to_resolve.is_empty() {
+        has_resolved = false;
+        for _ in 0..to_resolve.len() {
+            if let Some((name, value)) = to_resolve.pop_front() {
+                if let Some(color) = eval_color(state, value)? {
+                    state.colors.insert(name, color);
+                    has_resolved = true;
+                } else {
+                    to_resolve.push_back((name, value));
+                }
+            }
+        }
+    }
+    for (name, value) in &to_resolve {
+        for reference in references(value) {
+            let pending = to_resolve.iter().any(|(n, _)| n == reference);
+            if !pending && state.colors.get(reference).is_none() {
+                return Err(Error::UnknownColor(reference.to_owned(), name.to_owned()));
+            }
+        }
+    }
+    if !to_resolve.is_empty() {
+        let names: Vec<&str> = to_resolve.iter().map(|(n, _)| n.as_str()).collect();
+        return Err(Error::ColorCycle(names.join(", ")));
+    }
+    Ok(())
+}
+
 // Template?
 
+/// Section are dispatched, in order, to the template given by their `template` field,
+/// to the registered template with the same name and then to the built-in themes.
 pub fn process_section(
     state: &mut State,
+    templates: &Registry,
     name: &str,
     section: &Section,
-) -> Result<Option<Box<Theme>>, Error> {
-    match name.to_lowercase().as_str() {
+) -> Result<Option<Box<dyn Theme>>, Error> {
+    let lower = name.to_lowercase();
+    if lower != "colors" && lower != "define" && lower != "defined" {
+        let template = match section.get_path(state, "template") {
+            GetResult::Ok(path) => Some(template::read_template(path)?),
+            _ => templates.get(&lower).cloned(),
+        };
+        if let Some(template) = template {
+            let mut theme = TemplateTheme::new(name.to_owned(), template);
+            theme.create(state, section)?;
+            return Ok(Some(Box::new(theme)));
+        }
+    }
+    match lower.as_str() {
         "x11" | "xresources" => {
             let mut x11 = X11::new();
             x11.create(state, section)?;
@@ -178,7 +476,77 @@ pub fn process_section(
             x11.create(state, section)?;
             Ok(Some(Box::new(x11)))
         }
-        "define" => {
+        "alacritty" => {
+            let mut alacritty = Alacritty::new();
+            alacritty.create(state, section)?;
+            Ok(Some(Box::new(alacritty)))
+        }
+        "kitty" => {
+            let mut kitty = Kitty::new();
+            kitty.create(state, section)?;
+            Ok(Some(Box::new(kitty)))
+        }
+        "vim" | "neovim" | "nvim" => {
+            let mut vim = Vim::new(&lower);
+            vim.create(state, section)?;
+            Ok(Some(Box::new(vim)))
+        }
+        "i3" | "sway" => {
+            let mut wm = I3::new(&lower);
+            wm.create(state, section)?;
+            Ok(Some(Box::new(wm)))
+        }
+        "polybar" => {
+            let mut polybar = Polybar::new();
+            polybar.create(state, section)?;
+            Ok(Some(Box::new(polybar)))
+        }
+        "rofi" => {
+            let mut rofi = Rofi::new();
+            rofi.create(state, section)?;
+            Ok(Some(Box::new(rofi)))
+        }
+        "dunst" => {
+            let mut dunst = Dunst::new();
+            dunst.create(state, section)?;
+            Ok(Some(Box::new(dunst)))
+        }
+        "gtk" => {
+            let mut gtk = Gtk::new();
+            gtk.create(state, section)?;
+            Ok(Some(Box::new(gtk)))
+        }
+        "qt5ct" | "qt6ct" => {
+            let mut qt = Qt::new(&lower);
+            qt.create(state, section)?;
+            Ok(Some(Box::new(qt)))
+        }
+        "kde" => {
+            let mut kde = Kde::new();
+            kde.create(state, section)?;
+            Ok(Some(Box::new(kde)))
+        }
+        "tmux" => {
+            let mut tmux = Tmux::new();
+            tmux.create(state, section)?;
+            Ok(Some(Box::new(tmux)))
+        }
+        "vscode" | "vscodium" => {
+            let mut vscode = Vscode::new(&lower);
+            vscode.create(state, section)?;
+            Ok(Some(Box::new(vscode)))
+        }
+        "emacs" => {
+            let mut emacs = Emacs::new();
+            emacs.create(state, section)?;
+            Ok(Some(Box::new(emacs)))
+        }
+        "osc" => {
+            let mut osc = Osc::new();
+            osc.create(state, section)?;
+            Ok(Some(Box::new(osc)))
+        }
+        "define" | "defined" => {
             state.defined = section.values().to_owned();
             Ok(None)
         }
@@ -188,67 +556,312 @@ pub fn process_section(
                 if let Value::Section(ref s) = entry.value {
                     for sentry in s.values() {
                         let sname = format!("{}_{}", entry.name, sentry.name);
-                        if let Ok(color) = expect_color(&sentry.value) {
-                            state.colors.insert(sname, color);
-                        } else if let Value::Str(ref s) = sentry.value {
-                            to_resolve.push_back((sname.to_string(), s));
-                        }
-                        // TODO: else for error
-                    }
-                } else if let Ok(color) = expect_color(&entry.value) {
-                    state.colors.insert(entry.name.to_string(), color);
-                } else if let Value::Str(ref s) = entry.value {
-                    to_resolve.push_back((entry.name.to_string(), s));
-                }
-            }
-            let mut has_resolved = true;
-            while has_resolved && !to_resolve.is_empty() {
-                has_resolved = false;
-                for _ in 0..to_resolve.len() {
-                    if let Some((name, value)) = to_resolve.pop_front() {
-                        if let GetResult::Ok(color) = section.get_color(state, &value) {
-                            state.colors.insert(name.to_string(), color);
-                            has_resolved = true;
-                        } else {
-                            to_resolve.push_back((name, value));
-                        }
+                        add_color(state, &mut to_resolve, sname, &sentry.value)?;
                     }
+                } else {
+                    add_color(state, &mut to_resolve, entry.name.to_string(), &entry.value)?;
                 }
             }
+            resolve_colors(state, to_resolve)?;
             Ok(None)
         }
         _ => Err(Error::UnknownSection(name.to_owned())),
     }
 }
 
-pub fn process_state(config: &Config) -> State {
+pub fn process_state(config: &Config, name: &str) -> Result<State, Error> {
     let mut state = State {
+        name: name.to_owned(),
         colors: Map::new(),
         defined: Map::new(),
     };
     if let Some(defined) = config.sections().get("defined") {
-        let _ = process_section(&mut state, "defined", &defined);
+        process_section(&mut state, &Registry::new(), "defined", defined)?;
     }
     if let Some(colors) = config.sections().get("colors") {
-        let _ = process_section(&mut state, "colors", &colors);
+        process_section(&mut state, &Registry::new(), "colors", colors)?;
     }
-    state
+    Ok(state)
 }
 
-pub fn process_config(config: &mut Config) -> Result<Vec<Box<Theme>>, Error> {
+/// Themes of every section, a section which fails does not stop the others and its
+/// error is returned in its place.
+pub fn process_config(
+    config: &mut Config,
+    name: &str,
+    templates: &Registry,
+) -> Result<Vec<SectionTheme>, Error> {
     let mut result = Vec::new();
-    let mut state = process_state(config);
+    let mut state = process_state(config, name)?;
     for entry in config.sections() {
         if entry.name == "colors" || entry.name == "defined" {
             continue;
         }
-        match process_section(&mut state, &entry.name, &entry.value) {
+        match process_section(&mut state, templates, &entry.name, &entry.value) {
             Ok(Some(gen)) => {
-                result.push(gen);
+                result.push(Ok(gen));
             }
             Ok(None) | Err(Error::UnknownSection { .. }) => continue,
-            Err(e) => return Err(e),
+            Err(e) => result.push(Err(Error::Section(entry.name.clone(), Box::new(e)))),
         }
     }
     Ok(result)
 }
+
+/// Replace a leading `~` by the home directory.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (
+        path.starts_with("~/") || path == "~",
+        ::std::env::var_os("HOME"),
+    ) {
+        (true, Some(home)) => PathBuf::from(home).join(path[1..].trim_start_matches('/')),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// `name` in lowercase with the characters other than ASCII letters and digits
+/// replaced by `-`, for the file names and identifiers of generated themes.
+pub(crate) fn slug(name: &str) -> Result<String, Error> {
+    let slug: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    match slug.trim_matches('-') {
+        "" => Err(Error::InvalidName(name.to_owned())),
+        slug => Ok(slug.to_owned()),
+    }
+}
+
+/// `name` if it can be used as a file name: not empty, without a path separator and
+/// not hidden, which also rules out `.` and `..`.
+pub(crate) fn file_name(name: &str) -> Result<&str, Error> {
+    if name.is_empty() || name.starts_with('.') || name.contains('/') {
+        Err(Error::InvalidName(name.to_owned()))
+    } else {
+        Ok(name)
+    }
+}
+
+/// Replace the lines between `<comment> BEGIN themer` and `<comment> END themer` by
+/// `block`, the markers and the block are appended if they are missing.
+pub fn splice(content: &str, block: &str, comment: &str) -> String {
+    let begin = format!("{} BEGIN themer", comment);
+    let end = format!("{} END themer", comment);
+    let block = format!("{}\n{}\n{}\n", begin, block.trim_end_matches('\n'), end);
+    let lines: Vec<&str> = content.lines().collect();
+    let start = lines.iter().position(|l| l.trim() == begin);
+    let stop = start.and_then(|s| {
+        lines[s..]
+            .iter()
+            .position(|l| l.trim() == end)
+            .map(|e| s + e)
+    });
+    match (start, stop) {
+        (Some(start), Some(stop)) => {
+            let mut result = String::new();
+            for line in &lines[..start] {
+                result.push_str(line);
+                result.push('\n');
+            }
+            result.push_str(&block);
+            for line in &lines[stop + 1..] {
+                result.push_str(line);
+                result.push('\n');
+            }
+            result
+        }
+        _ if content.is_empty() => block,
+        _ => format!("{}\n\n{}", content.trim_end_matches('\n'), block),
+    }
+}
+
+/// `splice` a block into a file, which is created if it does not exist.
+pub fn splice_file<P: AsRef<Path>>(path: P, block: &str, comment: &str) -> Result<(), Error> {
+    let content = match fs::read_to_string(path.as_ref()) {
+        Ok(content) => content,
+        Err(ref e) if e.kind() == ::std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    fs::write(path, splice(&content, block, comment))?;
+    Ok(())
+}
+
+/// Look for `<name>.th` in each directory, in order. Names which are not a plain
+/// file name are rejected so themes are only read from these directories.
+pub fn find_theme<P: AsRef<Path>>(dirs: &[P], name: &str) -> Result<PathBuf, Error> {
+    let name = file_name(name)?;
+    dirs.iter()
+        .map(|dir| dir.as_ref().join(format!("{}.th", name)))
+        .find(|path| path.is_file())
+        .ok_or_else(|| Error::ThemeNotFound(name.to_owned()))
+}
+
+pub fn load_config<P: AsRef<Path>>(path: P) -> Result<Config, Error> {
+    config::read_config(path.as_ref())
+        .map_err(|e| Error::InvalidConfig(path.as_ref().display().to_string(), e))
+}
+
+/// Name of the active theme, as recorded by `set_current_theme`.
+pub fn current_theme<P: AsRef<Path>>(path: P) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_owned())
+        .filter(|s| !s.is_empty())
+}
+
+pub fn set_current_theme<P: AsRef<Path>>(path: P, name: &str) -> Result<(), Error> {
+    if let Some(parent) = path.as_ref().parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, format!("{}\n", name))?;
+    Ok(())
+}
+
+/// Fixtures shared by the tests of the targets.
+#[cfg(test)]
+pub(crate) mod test_utils {
+    use super::{Color, Error, State, Theme};
+
+    /// State of a theme named `name` with `colors`.
+    pub fn state(name: &str, colors: &[(&str, Color)]) -> State {
+        State {
+            name: name.to_owned(),
+            colors: colors
+                .iter()
+                .map(|(n, c)| (n.to_string(), c.clone()))
+                .collect(),
+            defined: Default::default(),
+        }
+    }
+
+    /// `theme` created from the first section of `config`.
+    pub fn create<T: Theme>(mut theme: T, state: &State, config: &str) -> Result<T, Error> {
+        let config = ::config::read_stream(&mut config.as_bytes()).unwrap();
+        let section = config.sections().into_iter().next().unwrap();
+        theme.create(state, &section.value)?;
+        Ok(theme)
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn state(buf: &str) -> Result<State, Error> {
+        let config = config::read_stream(&mut buf.as_bytes()).unwrap();
+        process_state(&config, "test")
+    }
+
+    #[test]
+    fn color_functions() {
+        let state = state(
+            "colors {
+                black #191d26
+                red hsl(0, 100%, 50%)
+                cursor bright_black
+                bright { black lighten(black, 20%) }
+                dim { red mix(red, black, 0.5) }
+            }",
+        )
+        .unwrap();
+        assert_eq!(state.colors.get("red"), Some(&Color::rgb(255, 0, 0)));
+        assert_eq!(
+            state.colors.get("bright_black"),
+            Some(&Color::rgb(65, 76, 100))
+        );
+        assert_eq!(state.colors.get("cursor"), Some(&Color::rgb(65, 76, 100)));
+        assert_eq!(state.colors.get("dim_red"), Some(&Color::rgb(140, 15, 19)));
+    }
+
+    #[test]
+    fn percent_arguments() {
+        let state = state(
+            "colors {
+                black #191d26
+                a lighten(black, 20)
+                b lighten(black, 20%)
+                c lighten(rgb(25, 29, 38), 0.2)
+                d shade(black, -50)
+            }",
+        )
+        .unwrap();
+        let a = state.colors.get("a");
+        assert_eq!(a, Some(&Color::rgb(65, 76, 100)));
+        assert_eq!(state.colors.get("b"), a);
+        assert_eq!(state.colors.get("c"), a);
+        assert_eq!(state.colors.get("d"), Some(&Color::rgb(140, 142, 147)));
+    }
+
+    #[test]
+    fn non_colors_skipped() {
+        let state = state("colors { black #000000 wallpaper /tmp/a.png opacity 80 }").unwrap();
+        assert_eq!(state.colors.get("black"), Some(&Color::rgb(0, 0, 0)));
+        assert_eq!(state.colors.get("wallpaper"), None);
+        assert_eq!(state.colors.get("opacity"), None);
+    }
+
+    #[test]
+    fn failed_sections_reported() {
+        let mut config = config::read_stream(
+            &mut "colors { black #000000 } foo { template /nonexistent } x11 {}".as_bytes(),
+        )
+        .unwrap();
+        let themes = process_config(&mut config, "test", &Registry::new()).unwrap();
+        assert_eq!(themes.len(), 2);
+        match themes[0] {
+            Err(Error::Section(ref name, _)) => assert_eq!(name, "foo"),
+            ref other => panic!("expected a section error, found {:?}", other),
+        }
+        assert_eq!(themes[1].as_ref().unwrap().name(), "x11");
+    }
+
+    #[test]
+    fn unresolved_colors() {
+        assert_eq!(
+            state("colors { a b b lighten(a, 10%) }")
+                .unwrap_err()
+                .to_string(),
+            "cycle between colors a, b"
+        );
+        assert_eq!(
+            state("colors { a b b darken(c, 10%) }")
+                .unwrap_err()
+                .to_string(),
+            "unknown color `c` used by `b`"
+        );
+    }
+
+    #[test]
+    fn splice_block() {
+        let spliced = splice("a = 1\n", "b = 2\n", "#");
+        assert_eq!(spliced, "a = 1\n\n# BEGIN themer\nb = 2\n# END themer\n");
+        assert_eq!(
+            splice(&format!("{}c = 3\n", spliced), "b = 4", "#"),
+            "a = 1\n\n# BEGIN themer\nb = 4\n# END themer\nc = 3\n"
+        );
+        assert_eq!(
+            splice("", "b = 2", ";"),
+            "; BEGIN themer\nb = 2\n; END themer\n"
+        );
+    }
+
+    #[test]
+    fn theme_names() {
+        let dir = ::std::env::temp_dir().join(format!("themer-themes-{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("ayu.th"), "colors {}\n").unwrap();
+        assert_eq!(find_theme(&[&dir], "ayu").unwrap(), dir.join("ayu.th"));
+        for name in &["", "../ayu", "/etc/ayu", ".ayu", "themes/ayu"] {
+            match find_theme(&[&dir], name) {
+                Err(Error::InvalidName(ref n)) => assert_eq!(n, name),
+                other => panic!("expected an invalid name, got {:?}", other),
+            }
+        }
+        assert_eq!(
+            find_theme(&[&dir], "nord").unwrap_err().to_string(),
+            "theme `nord` not found"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+//! Contrast checks between the colors of a theme.
+
+use super::{Color, Error, GetResult, Getter, State};
+use color::Oklch;
+use config::Section;
+use x11::ANSI;
+
+/// Minimum contrasts a pair of colors must reach.
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// WCAG 2.1 contrast ratio, 4.5 for AA normal text
+    pub threshold: f64,
+    /// Absolute APCA lightness contrast, not checked if missing
+    pub apca: Option<f64>,
+    /// Colors left out of the checks
+    pub skip: Vec<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            threshold: 4.5,
+            apca: None,
+            skip: Vec::new(),
+        }
+    }
+}
+
+impl Options {
+    /// Read the `lint` section of a theme, whose `skip` field lists the colors
+    /// to leave out separated by spaces, e.g. `lint { skip "black" }`.
+    pub fn configure(&mut self, state: &State, section: &Section) {
+        if let GetResult::Ok(skip) = section.get_str(state, "skip") {
+            self.skip = skip.split_whitespace().map(|s| s.to_owned()).collect();
+        }
+    }
+
+    fn passes(&self, foreground: &Color, background: &Color) -> bool {
+        foreground.contrast(background) >= self.threshold
+            && self
+                .apca
+                .map(|min| foreground.apca(background).abs() >= min)
+                .unwrap_or(true)
+    }
+}
+
+/// Contrast of one color against the background.
+#[derive(Clone, Debug)]
+pub struct Check {
+    pub foreground: String,
+    pub background: String,
+    pub foreground_color: Color,
+    pub background_color: Color,
+    /// WCAG 2.1 contrast ratio
+    pub contrast: f64,
+    /// APCA lightness contrast, only computed when checked
+    pub apca: Option<f64>,
+    pub passed: bool,
+    /// Closest color with the same hue that passes, if the check failed
+    pub suggestion: Option<Color>,
+}
+
+/// Check `foreground` and the 16 ANSI colors against `background`, missing colors
+/// and the ones in `options.skip` are left out.
+pub fn lint(state: &State, options: &Options) -> Result<Vec<Check>, Error> {
+    let background = state
+        .colors
+        .get("background")
+        .ok_or_else(|| Error::MissingColor(state.name.to_owned(), "background".to_owned()))?;
+    Ok(::std::iter::once(&"foreground")
+        .chain(ANSI)
+        .filter(|name| !options.skip.iter().any(|s| s == *name))
+        .filter_map(|name| state.colors.get(name).map(|c| (name, c)))
+        .map(|(name, foreground)| {
+            let passed = options.passes(foreground, background);
+            Check {
+                foreground: name.to_string(),
+                background: "background".to_owned(),
+                foreground_color: foreground.clone(),
+                background_color: background.clone(),
+                contrast: foreground.contrast(background),
+                apca: options.apca.map(|_| foreground.apca(background)),
+                passed,
+                suggestion: if passed {
+                    None
+                } else {
+                    suggest(foreground, background, options)
+                },
+            }
+        })
+        .collect())
+}
+
+/// Move the OKLCH lightness of `foreground` away from `background` until the
+/// pair passes, trying the other direction if it never does.
+pub fn suggest(foreground: &Color, background: &Color, options: &Options) -> Option<Color> {
+    let start = foreground.to_oklch();
+    let lighter = start.l >= background.to_oklch().l;
+    let search = |up: bool| {
+        (1..=200)
+            .map(|i| {
+                let step = f64::from(i) * 0.005;
+                Oklch {
+                    l: if up { start.l + step } else { start.l - step },
+                    ..start
+                }
+            })
+            .take_while(|c| c.l >= 0.0 && c.l <= 1.0)
+            .map(|c| Color::from(c).with_alpha(f64::from(foreground.alpha()) / 255.0))
+            .find(|c| options.passes(c, background))
+    };
+    search(lighter).or_else(|| search(!lighter))
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use test_utils::state;
+
+    #[test]
+    fn failing_pairs() {
+        let state = state(
+            "test",
+            &[
+                ("background", Color::rgb(0x1f, 0x24, 0x30)),
+                ("foreground", Color::rgb(0xcb, 0xcc, 0xc6)),
+                ("black", Color::rgb(0x1f, 0x24, 0x30)),
+                ("bright_black", Color::rgb(0x44, 0x4b, 0x5a)),
+            ],
+        );
+        let options = Options {
+            threshold: 4.5,
+            apca: Some(45.0),
+            skip: Vec::new(),
+        };
+        let checks = lint(&state, &options).unwrap();
+        assert_eq!(checks.len(), 3);
+        assert!(checks[0].passed && checks[0].suggestion.is_none());
+        assert!(checks[0].apca.unwrap() < -45.0);
+        assert_eq!(checks[1].foreground, "black");
+        assert!(!checks[1].passed);
+        assert_eq!(checks[2].foreground, "bright_black");
+        assert!(!checks[2].passed);
+        let suggestion = checks[2].suggestion.clone().unwrap();
+        assert!(options.passes(&suggestion, &checks[2].background_color));
+        assert!(suggestion.luminance() > checks[2].foreground_color.luminance());
+    }
+
+    #[test]
+    fn skipped_colors() {
+        let state = state(
+            "test",
+            &[
+                ("background", Color::rgb(0x1f, 0x24, 0x30)),
+                ("black", Color::rgb(0x1f, 0x24, 0x30)),
+                ("bright_black", Color::rgb(0x44, 0x4b, 0x5a)),
+            ],
+        );
+        let config =
+            ::config::read_stream(&mut "lint { skip \"black bright_black\" }".as_bytes()).unwrap();
+        let mut options = Options::default();
+        options.configure(&state, config.sections().get("lint").unwrap());
+        assert_eq!(options.skip, vec!["black", "bright_black"]);
+        assert!(lint(&state, &options).unwrap().is_empty());
+    }
+
+    #[test]
+    fn missing_background() {
+        let state = state("test", &[("foreground", Color::rgb(0xcb, 0xcc, 0xc6))]);
+        match lint(&state, &Options::default()) {
+            Err(Error::MissingColor(ref name, ref color)) => {
+                assert_eq!((name.as_str(), color.as_str()), ("test", "background"))
+            }
+            other => panic!("expected a missing background, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn impossible() {
+        let background = Color::rgb(0x80, 0x80, 0x80);
+        let options = Options {
+            threshold: 21.0,
+            apca: None,
+            skip: Vec::new(),
+        };
+        assert_eq!(suggest(&background, &background, &options), None);
+    }
+}
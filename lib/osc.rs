@@ -0,0 +1,195 @@
+use super::{Applied, Color, Error, GetResult, Getter, State, Theme};
+use config::Section;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use template::Format;
+use x11::{mapped_color, COLOR_MAP};
+
+const AVAILABLE_FIELDS: &[&str] = &[
+    "all_ttys",
+    "tmux",
+    "output",
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright_black",
+    "bright_red",
+    "bright_green",
+    "bright_yellow",
+    "bright_blue",
+    "bright_magenta",
+    "bright_cyan",
+    "bright_white",
+    "foreground",
+    "background",
+    "cursor",
+];
+
+/// Recolors running terminals with OSC 4 (palette), 10 (foreground), 11
+/// (background) and 12 (cursor) escape sequences.
+#[derive(Default, Debug)]
+pub struct Osc {
+    /// Write to every `/dev/pts/*` of the user instead of the current terminal
+    all_ttys: bool,
+    /// Wrap the sequences in a tmux passthrough
+    tmux: bool,
+    /// File also receiving the sequences, e.g. to restore them in new shells
+    output: Option<PathBuf>,
+    /// OSC parameters and their colors, e.g. `4;1` for red
+    colors: Vec<(String, Color)>,
+}
+
+impl Osc {
+    pub fn new() -> Self {
+        Osc::default()
+    }
+}
+
+/// OSC parameter setting the color known as `name` by X11.
+fn parameter(name: &str) -> String {
+    match name {
+        "foreground" => "10".to_owned(),
+        "background" => "11".to_owned(),
+        "cursorColor" => "12".to_owned(),
+        color => format!("4;{}", color.trim_start_matches("color")),
+    }
+}
+
+/// Terminals of `/dev/pts` owned by the user running themer.
+fn user_ttys() -> Result<Vec<PathBuf>, Error> {
+    // Processes are owned by their effective user.
+    let uid = fs::metadata("/proc/self")?.uid();
+    let mut ttys = Vec::new();
+    for entry in fs::read_dir("/dev/pts")? {
+        let path = entry?.path();
+        // A terminal closed since the directory was read is skipped.
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        let numbered = path
+            .file_name()
+            .map(|n| n.to_string_lossy().chars().all(|c| c.is_ascii_digit()))
+            .unwrap_or(false);
+        if numbered && metadata.file_type().is_char_device() && metadata.uid() == uid {
+            ttys.push(path);
+        }
+    }
+    ttys.sort();
+    Ok(ttys)
+}
+
+fn write_tty(path: &Path, sequences: &str) -> Result<(), Error> {
+    OpenOptions::new()
+        .write(true)
+        .open(path)?
+        .write_all(sequences.as_bytes())?;
+    Ok(())
+}
+
+impl Theme for Osc {
+    fn name(&self) -> &str {
+        "osc"
+    }
+
+    fn available_fields(&self) -> &[&str] {
+        AVAILABLE_FIELDS
+    }
+
+    fn create(&mut self, state: &State, section: &Section) -> Result<(), Error> {
+        if let GetResult::Ok(all) = section.get_bool(state, "all_ttys") {
+            self.all_ttys = all;
+        }
+        self.tmux = section
+            .get_bool(state, "tmux")
+            .to_option()
+            .unwrap_or_else(|| ::std::env::var_os("TMUX").is_some());
+        self.output = section.get_path(state, "output").to_option();
+        for (color, newname) in COLOR_MAP {
+            if let Some(c) = mapped_color(state, section, color) {
+                self.colors.push((parameter(newname), c));
+            }
+        }
+        Ok(())
+    }
+
+    fn generated(&self) -> Result<String, Error> {
+        let mut buf = String::new();
+        for (parameter, c) in &self.colors {
+            let sequence = format!("\x1b]{};{}\x1b\\", parameter, Format::Hex.format(c));
+            if self.tmux {
+                buf.push_str(&format!(
+                    "\x1bPtmux;{}\x1b\\",
+                    sequence.replace('\x1b', "\x1b\x1b")
+                ));
+            } else {
+                buf.push_str(&sequence);
+            }
+        }
+        Ok(buf)
+    }
+
+    fn apply(&self) -> Result<Applied, Error> {
+        let generated = self.generated()?;
+        if let Some(ref path) = self.output {
+            File::create(path)?.write_all(generated.as_bytes())?;
+        }
+        let output = if self.all_ttys {
+            // Terminals we cannot write to, e.g. closing ones, are skipped.
+            for tty in user_ttys()? {
+                let _ = write_tty(&tty, &generated);
+            }
+            PathBuf::from("/dev/pts")
+        } else {
+            let tty = PathBuf::from("/dev/tty");
+            write_tty(&tty, &generated)?;
+            tty
+        };
+        Ok(Applied {
+            output,
+            command: None,
+            status: None,
+        })
+    }
+
+    fn output(&mut self) -> Option<&PathBuf> {
+        self.output.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use test_utils::{create, state};
+
+    #[test]
+    fn sequences() {
+        let state = state(
+            "test",
+            &[
+                ("red", Color::rgb(0xea, 0x59, 0x65)),
+                ("bright_black", Color::rgb(0x55, 0x55, 0x55)),
+                ("background", Color::rgb(0x19, 0x1d, 0x26)),
+            ],
+        );
+        let mut osc = create(Osc::new(), &state, "osc { tmux no }").unwrap();
+        assert_eq!(
+            osc.generated().unwrap(),
+            "\x1b]4;1;#ea5965\x1b\\\x1b]4;8;#555555\x1b\\\x1b]11;#191d26\x1b\\"
+        );
+
+        osc.tmux = true;
+        osc.colors.truncate(1);
+        assert_eq!(
+            osc.generated().unwrap(),
+            "\x1bPtmux;\x1b\x1b]4;1;#ea5965\x1b\x1b\\\x1b\\"
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use super::{run, splice_file, ui_roles, Applied, Color, Error, GetResult, Getter, State, Theme};
+use config::map::Map;
+use config::Section;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+use template::Format;
+use x11::{mapped_color, ANSI};
+
+const AVAILABLE_FIELDS: &[&str] = &[
+    "output",
+    "splice",
+    "reload",
+    "background",
+    "foreground",
+    "surface",
+    "inactive",
+    "accent",
+    "urgent",
+];
+
+/// A `[colors]` section, used in the bar config as `${colors.accent}`.
+#[derive(Default, Debug)]
+pub struct Polybar {
+    /// File to `include-file` from the main config
+    output: Option<PathBuf>,
+    /// Existing config in which the section is spliced
+    splice: Option<PathBuf>,
+    /// Restart running bars once written
+    reload: bool,
+    roles: Map<Color>,
+    palette: Vec<(String, Color)>,
+}
+
+impl Polybar {
+    pub fn new() -> Self {
+        Polybar::default()
+    }
+}
+
+impl Theme for Polybar {
+    fn name(&self) -> &str {
+        "polybar"
+    }
+
+    fn available_fields(&self) -> &[&str] {
+        AVAILABLE_FIELDS
+    }
+
+    fn create(&mut self, state: &State, section: &Section) -> Result<(), Error> {
+        self.output = section.get_path(state, "output").to_option();
+        self.splice = section.get_path(state, "splice").to_option();
+        if let GetResult::Ok(reload) = section.get_bool(state, "reload") {
+            self.reload = reload;
+        }
+        self.roles = ui_roles(state, section)?;
+        for name in ANSI {
+            if let Some(c) = mapped_color(state, section, name) {
+                self.palette.push((name.to_string(), c));
+            }
+        }
+        Ok(())
+    }
+
+    fn generated(&self) -> Result<String, Error> {
+        let mut buf = vec!["[colors]".to_owned()];
+        for entry in &self.roles {
+            buf.push(format!(
+                "{} = {}",
+                entry.name,
+                Format::HexArgb.translucent(&entry.value)
+            ));
+        }
+        for (name, c) in &self.palette {
+            buf.push(format!("{} = {}", name, Format::HexArgb.translucent(c)));
+        }
+        Ok(buf.join("\n") + "\n")
+    }
+
+    fn apply(&self) -> Result<Applied, Error> {
+        let generated = self.generated()?;
+        let path = match self.splice {
+            Some(ref path) => {
+                splice_file(path, &generated, ";")?;
+                path.to_owned()
+            }
+            None => {
+                let path = self
+                    .output
+                    .clone()
+                    .unwrap_or_else(|| ::expand_home("~/.config/polybar/colors.ini"));
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                File::create(&path)?.write_all(generated.as_bytes())?;
+                path
+            }
+        };
+        if !self.reload {
+            return Ok(Applied {
+                output: path,
+                command: None,
+                status: None,
+            });
+        }
+        let status = run(Command::new("polybar-msg").args(["cmd", "restart"]))?;
+        Ok(Applied {
+            command: Some("polybar-msg cmd restart".to_owned()),
+            output: path,
+            status: Some(status),
+        })
+    }
+
+    fn output(&mut self) -> Option<&PathBuf> {
+        self.output.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use test_utils::{create, state};
+
+    #[test]
+    fn colors() {
+        let state = state(
+            "test",
+            &[
+                ("background", Color::rgba(0x19, 0x1d, 0x26, 0xcc)),
+                ("foreground", Color::rgb(0xd0, 0xce, 0xc3)),
+                ("red", Color::rgb(0xea, 0x59, 0x65)),
+            ],
+        );
+        let polybar = create(Polybar::new(), &state, "polybar { accent #ffcc66 }").unwrap();
+        assert_eq!(
+            polybar.generated().unwrap(),
+            "\
+[colors]
+background = #cc191d26
+foreground = #d0cec3
+accent = #ffcc66
+urgent = #ea5965
+surface = #d22f3239
+red = #ea5965
+"
+        );
+    }
+}
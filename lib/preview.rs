@@ -0,0 +1,185 @@
+//! Render the colors of a theme in the terminal.
+
+use super::{Color, State};
+use std::env;
+use std::fmt::Write;
+use template::Format;
+use x11::ANSI;
+
+const SPECIAL: &[&str] = &["foreground", "background", "cursor"];
+
+const RESET: &str = "\x1b[0m";
+
+/// Whether the terminal advertises 24-bit colors through `COLORTERM`.
+pub fn truecolor() -> bool {
+    env::var("COLORTERM")
+        .map(|v| v == "truecolor" || v == "24bit")
+        .unwrap_or(false)
+}
+
+/// Escape sequences for the colors of a state, approximated with the 256 colors
+/// palette if the terminal does not support 24-bit colors.
+struct Painter<'a> {
+    state: &'a State,
+    truecolor: bool,
+}
+
+impl<'a> Painter<'a> {
+    fn escape(&self, layer: u8, c: &Color) -> String {
+        if self.truecolor {
+            format!("\x1b[{}8;2;{};{};{}m", layer, c.red(), c.green(), c.blue())
+        } else {
+            format!("\x1b[{}8;5;{}m", layer, c.to_ansi256())
+        }
+    }
+
+    /// Foreground of the named color, or nothing if the theme does not define it.
+    fn fg(&self, name: &str) -> String {
+        self.state
+            .colors
+            .get(name)
+            .map(|c| self.escape(3, c))
+            .unwrap_or_default()
+    }
+
+    fn bg(&self, name: &str) -> String {
+        self.state
+            .colors
+            .get(name)
+            .map(|c| self.escape(4, c))
+            .unwrap_or_default()
+    }
+
+    /// Swatch and hex value of a color, blank if it is missing.
+    fn swatch(&self, name: &str) -> String {
+        match self.state.colors.get(name) {
+            Some(c) => format!(
+                "{}      {} {:<16}{}",
+                self.escape(4, c),
+                RESET,
+                name,
+                Format::Hex.format(c)
+            ),
+            None => format!("       {:<23}", name),
+        }
+    }
+
+    /// A line of sample text on the theme background. Spans are `(color, text)`
+    /// pairs, an empty color being the foreground.
+    fn line(&self, out: &mut String, spans: &[(&str, &str)]) {
+        let width: usize = spans.iter().map(|(_, t)| t.chars().count()).sum();
+        let _ = write!(out, "  {}{}", self.bg("background"), self.fg("foreground"));
+        for (color, text) in spans {
+            let color = if color.is_empty() {
+                "foreground"
+            } else {
+                color
+            };
+            let _ = write!(out, "{}{}", self.fg(color), text);
+        }
+        let _ = writeln!(out, "{:1$}{2}", "", 48usize.saturating_sub(width), RESET);
+    }
+}
+
+/// Swatches of every color of `state`, followed by sample code, `ls` and diff
+/// output drawn with them.
+pub fn render(state: &State, truecolor: bool) -> String {
+    let painter = Painter { state, truecolor };
+    let mut out = String::new();
+    let _ = writeln!(out, "{}\n", state.name);
+    let (normal, bright) = ANSI.split_at(8);
+    for (name, bright) in normal.iter().zip(bright) {
+        let _ = writeln!(
+            out,
+            "  {}   {}",
+            painter.swatch(name),
+            painter.swatch(bright)
+        );
+    }
+    let _ = writeln!(out);
+    for name in SPECIAL {
+        let _ = writeln!(out, "  {}", painter.swatch(name));
+    }
+    let others: Vec<&str> = state
+        .colors
+        .into_iter()
+        .map(|e| e.name.as_str())
+        .filter(|n| !SPECIAL.contains(n) && !ANSI.contains(n))
+        .collect();
+    if !others.is_empty() {
+        let _ = writeln!(out);
+        for name in others {
+            let _ = writeln!(out, "  {}", painter.swatch(name));
+        }
+    }
+
+    let _ = writeln!(out);
+    let samples: &[&[(&str, &str)]] = &[
+        &[("bright_black", "// Print the sum of the arguments")],
+        &[("magenta", "fn "), ("blue", "main"), ("", "() {")],
+        &[
+            ("magenta", "    let "),
+            ("", "sum: "),
+            ("yellow", "u32"),
+            ("", " = "),
+            ("cyan", "args"),
+            ("", "().sum();"),
+        ],
+        &[
+            ("cyan", "    println!"),
+            ("", "("),
+            ("green", "\"sum: {}\""),
+            ("", ", sum + "),
+            ("yellow", "1"),
+            ("", ");"),
+        ],
+        &[("", "}")],
+        &[("", "")],
+        &[
+            ("", "drwxr-xr-x  "),
+            ("bright_blue", "src/"),
+            ("", "  "),
+            ("bright_green", "build.sh"),
+            ("", "  "),
+            ("bright_cyan", "latest"),
+            ("", " -> "),
+            ("bright_blue", "v0.1/"),
+            ("", "  "),
+            ("red", "dist.tar.gz"),
+        ],
+        &[("", "")],
+        &[("bright_white", "diff --git a/lib.rs b/lib.rs")],
+        &[("cyan", "@@ -1,2 +1,2 @@")],
+        &[("red", "-let color = \"#000000\";")],
+        &[("green", "+let color = \"#ffffff\";")],
+        &[("", " // unchanged")],
+    ];
+    for spans in samples {
+        painter.line(&mut out, spans);
+    }
+    out
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use test_utils::state;
+
+    #[test]
+    fn fallback() {
+        let state = state(
+            "test",
+            &[
+                ("background", Color::rgb(0x19, 0x1d, 0x26)),
+                ("bright_red", Color::rgb(0xea, 0x59, 0x65)),
+                ("accent", Color::rgb(0xff, 0, 0)),
+            ],
+        );
+        let truecolor = render(&state, true);
+        assert!(truecolor.contains("\x1b[48;2;234;89;101m      \x1b[0m bright_red"));
+        assert!(truecolor.contains("\x1b[48;2;255;0;0m      \x1b[0m accent"));
+        let fallback = render(&state, false);
+        assert!(fallback.contains("\x1b[48;5;196m      \x1b[0m accent"));
+        assert!(!fallback.contains(";2;"));
+    }
+}
@@ -0,0 +1,465 @@
+use super::{roles, run, ui_roles, Applied, Color, Error, GetResult, Getter, State, Theme};
+use config::map::Map;
+use config::Section;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use template::Format;
+
+const QT_FIELDS: &[&str] = &[
+    "output",
+    "background",
+    "foreground",
+    "surface",
+    "inactive",
+    "accent",
+    "urgent",
+    "link",
+    "visited",
+    "window_text",
+    "button",
+    "light",
+    "midlight",
+    "dark",
+    "mid",
+    "text",
+    "bright_text",
+    "button_text",
+    "base",
+    "window",
+    "shadow",
+    "highlight",
+    "highlighted_text",
+    "link_visited",
+    "alternate_base",
+    "no_role",
+    "tooltip_base",
+    "tooltip_text",
+    "placeholder_text",
+];
+
+const KDE_FIELDS: &[&str] = &[
+    "name",
+    "output",
+    "reload",
+    "background",
+    "foreground",
+    "surface",
+    "inactive",
+    "accent",
+    "urgent",
+    "warning",
+    "success",
+    "link",
+    "visited",
+    "window_text",
+    "button",
+    "text",
+    "button_text",
+    "base",
+    "window",
+    "highlight",
+    "highlighted_text",
+    "link_visited",
+    "alternate_base",
+    "tooltip_base",
+    "tooltip_text",
+    "placeholder_text",
+    "negative",
+    "neutral",
+    "positive",
+];
+
+/// Roles used on top of `UI_ROLES`.
+const EXTRA_ROLES: &[(&str, &[&str])] = &[
+    ("link", &["link", "blue"]),
+    ("visited", &["visited", "magenta"]),
+    ("warning", &["warning", "yellow"]),
+    ("success", &["success", "green"]),
+];
+
+/// Palette roles in the order of `QPalette::ColorRole`, with the roles they default
+/// to. The shades of `button` have none, they are derived from it.
+const PALETTE: &[(&str, &[&str])] = &[
+    ("window_text", &["foreground"]),
+    ("button", &["surface", "background"]),
+    ("light", &[]),
+    ("midlight", &[]),
+    ("dark", &[]),
+    ("mid", &[]),
+    ("text", &["foreground"]),
+    ("bright_text", &["foreground"]),
+    ("button_text", &["foreground"]),
+    ("base", &["background"]),
+    ("window", &["background"]),
+    ("shadow", &[]),
+    ("highlight", &["accent", "foreground"]),
+    ("highlighted_text", &["background"]),
+    ("link", &["link", "accent", "foreground"]),
+    ("link_visited", &["visited", "link", "accent", "foreground"]),
+    ("alternate_base", &["surface", "background"]),
+    ("no_role", &["background"]),
+    ("tooltip_base", &["surface", "background"]),
+    ("tooltip_text", &["foreground"]),
+    ("placeholder_text", &["inactive", "foreground"]),
+    // Qt 6.6 and later
+    ("accent", &["accent", "foreground"]),
+];
+
+/// Roles of KDE schemes which are not part of the palette.
+const STATUS: &[(&str, &[&str])] = &[
+    ("negative", &["urgent", "foreground"]),
+    ("neutral", &["warning", "foreground"]),
+    ("positive", &["success", "foreground"]),
+];
+
+/// Shades of `button`, as a mix with white or black.
+const SHADES: &[(&str, bool, f64)] = &[
+    ("light", true, 0.25),
+    ("midlight", true, 0.12),
+    ("dark", false, 0.5),
+    ("mid", false, 0.25),
+    ("shadow", false, 0.75),
+];
+
+/// Roles replaced in the disabled color group.
+const DISABLED: &[(&str, &str)] = &[
+    ("window_text", "placeholder_text"),
+    ("text", "placeholder_text"),
+    ("bright_text", "placeholder_text"),
+    ("button_text", "placeholder_text"),
+    ("highlight", "button"),
+];
+
+/// Resolve `PALETTE` and `STATUS`: a role is taken from the section, then from the
+/// first of its defaults which is known.
+fn palette(state: &State, section: &Section) -> Result<Map<Color>, Error> {
+    let mut known = ui_roles(state, section)?;
+    for entry in &roles(state, section, EXTRA_ROLES)? {
+        known.insert(entry.name.to_owned(), entry.value.clone());
+    }
+    let table: Vec<(&str, &[&str])> = PALETTE
+        .iter()
+        .chain(STATUS)
+        .map(|(role, _)| (*role, &[][..]))
+        .collect();
+    let overrides = roles(state, section, &table)?;
+    let mut palette = Map::new();
+    for (role, defaults) in PALETTE.iter().chain(STATUS) {
+        if defaults.is_empty() {
+            continue;
+        }
+        let color = overrides
+            .get(role)
+            .or_else(|| defaults.iter().filter_map(|name| known.get(name)).next())
+            .cloned();
+        match color {
+            Some(c) => palette.insert(role.to_string(), c),
+            None => {
+                let missing = defaults[defaults.len() - 1];
+                return Err(Error::UnknownColor(missing.to_owned(), role.to_string()));
+            }
+        }
+    }
+    if let Some(button) = palette.get("button").cloned() {
+        for (role, lighter, amount) in SHADES {
+            let color = match overrides.get(role) {
+                Some(c) => c.clone(),
+                None if *lighter => button.mix(&Color::rgb(255, 255, 255), *amount),
+                None => button.mix(&Color::rgb(0, 0, 0), *amount),
+            };
+            palette.insert(role.to_string(), color);
+        }
+    }
+    Ok(palette)
+}
+
+/// A color scheme for qt5ct or qt6ct, to select in their settings. The file name
+/// does not change with the theme so the selection is kept.
+#[derive(Debug)]
+pub struct Qt {
+    /// `qt5ct` or `qt6ct`
+    program: String,
+    output: Option<PathBuf>,
+    palette: Map<Color>,
+}
+
+impl Qt {
+    pub fn new(program: &str) -> Self {
+        Qt {
+            program: program.to_owned(),
+            output: None,
+            palette: Map::new(),
+        }
+    }
+
+    /// One color group, every role is resolved by `palette`. `accent` is only known
+    /// to qt6ct.
+    fn group(&self, replaced: &[(&str, &str)]) -> String {
+        let count = if self.program == "qt6ct" {
+            PALETTE.len()
+        } else {
+            PALETTE.len() - 1
+        };
+        let colors: Vec<String> = PALETTE[..count]
+            .iter()
+            .filter_map(|(role, _)| {
+                let role = replaced
+                    .iter()
+                    .find(|(from, _)| from == role)
+                    .map(|(_, to)| to)
+                    .unwrap_or(role);
+                self.palette.get(role).map(|c| Format::HexArgb.format(c))
+            })
+            .collect();
+        colors.join(", ")
+    }
+}
+
+impl Theme for Qt {
+    fn name(&self) -> &str {
+        &self.program
+    }
+
+    fn available_fields(&self) -> &[&str] {
+        QT_FIELDS
+    }
+
+    fn create(&mut self, state: &State, section: &Section) -> Result<(), Error> {
+        self.output = section.get_path(state, "output").to_option();
+        self.palette = palette(state, section)?;
+        Ok(())
+    }
+
+    fn generated(&self) -> Result<String, Error> {
+        Ok(format!(
+            "[ColorScheme]\nactive_colors={}\ndisabled_colors={}\ninactive_colors={}\n",
+            self.group(&[]),
+            self.group(DISABLED),
+            self.group(&[])
+        ))
+    }
+
+    fn apply(&self) -> Result<Applied, Error> {
+        let path = self.output.clone().unwrap_or_else(|| {
+            ::expand_home(&format!("~/.config/{}/colors/themer.conf", self.program))
+        });
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, self.generated()?)?;
+        // Applications read the scheme when they start.
+        Ok(Applied {
+            output: path,
+            command: None,
+            status: None,
+        })
+    }
+
+    fn output(&mut self) -> Option<&PathBuf> {
+        self.output.as_ref()
+    }
+}
+
+/// Color sets of KDE schemes with their background, alternate background and
+/// foreground.
+const SETS: &[(&str, [&str; 3])] = &[
+    ("View", ["base", "alternate_base", "text"]),
+    ("Window", ["window", "alternate_base", "window_text"]),
+    ("Button", ["button", "alternate_base", "button_text"]),
+    ("Selection", ["highlight", "highlight", "highlighted_text"]),
+    (
+        "Tooltip",
+        ["tooltip_base", "alternate_base", "tooltip_text"],
+    ),
+    ("Complementary", ["window", "alternate_base", "window_text"]),
+    ("Header", ["window", "alternate_base", "window_text"]),
+];
+
+/// Keys shared by every set.
+const SET_KEYS: &[(&str, &str)] = &[
+    ("ForegroundInactive", "placeholder_text"),
+    ("ForegroundActive", "highlight"),
+    ("ForegroundLink", "link"),
+    ("ForegroundVisited", "link_visited"),
+    ("ForegroundNegative", "negative"),
+    ("ForegroundNeutral", "neutral"),
+    ("ForegroundPositive", "positive"),
+    ("DecorationFocus", "highlight"),
+    ("DecorationHover", "highlight"),
+];
+
+/// Window decoration keys.
+const WM_KEYS: &[(&str, &str)] = &[
+    ("activeBackground", "window"),
+    ("activeForeground", "window_text"),
+    ("inactiveBackground", "window"),
+    ("inactiveForeground", "placeholder_text"),
+];
+
+/// A KDE color scheme, installed in `~/.local/share/color-schemes`.
+#[derive(Default, Debug)]
+pub struct Kde {
+    /// Name of the scheme, the one of the theme by default
+    scheme_name: String,
+    output: Option<PathBuf>,
+    /// Apply the scheme with `plasma-apply-colorscheme` once written
+    reload: bool,
+    palette: Map<Color>,
+}
+
+impl Kde {
+    pub fn new() -> Self {
+        Kde::default()
+    }
+
+    fn rgb(&self, role: &str) -> String {
+        match self.palette.get(role) {
+            Some(c) => format!("{},{},{}", c.red(), c.green(), c.blue()),
+            None => String::new(),
+        }
+    }
+}
+
+impl Theme for Kde {
+    fn name(&self) -> &str {
+        "kde"
+    }
+
+    fn available_fields(&self) -> &[&str] {
+        KDE_FIELDS
+    }
+
+    fn create(&mut self, state: &State, section: &Section) -> Result<(), Error> {
+        self.scheme_name = match section.get_str(state, "name") {
+            GetResult::Ok(name) => name,
+            _ => state.name.to_owned(),
+        };
+        self.output = section.get_path(state, "output").to_option();
+        if let GetResult::Ok(reload) = section.get_bool(state, "reload") {
+            self.reload = reload;
+        }
+        self.palette = palette(state, section)?;
+        Ok(())
+    }
+
+    fn generated(&self) -> Result<String, Error> {
+        let mut buf = Vec::new();
+        for (set, [bg, alternate, fg]) in SETS {
+            buf.push(format!("[Colors:{}]", set));
+            buf.push(format!("BackgroundNormal={}", self.rgb(bg)));
+            buf.push(format!("BackgroundAlternate={}", self.rgb(alternate)));
+            buf.push(format!("ForegroundNormal={}", self.rgb(fg)));
+            for (key, role) in SET_KEYS {
+                // The selection is drawn on the highlight.
+                let role = if *set == "Selection" && *key == "ForegroundActive" {
+                    fg
+                } else {
+                    role
+                };
+                buf.push(format!("{}={}", key, self.rgb(role)));
+            }
+            buf.push(String::new());
+        }
+        buf.push("[General]".to_owned());
+        buf.push(format!("ColorScheme={}", self.scheme_name));
+        buf.push(format!("Name={}", self.scheme_name));
+        buf.push(String::new());
+        buf.push("[WM]".to_owned());
+        for (key, role) in WM_KEYS {
+            buf.push(format!("{}={}", key, self.rgb(role)));
+        }
+        Ok(buf.join("\n") + "\n")
+    }
+
+    fn apply(&self) -> Result<Applied, Error> {
+        let path = self.output.clone().unwrap_or_else(|| {
+            ::expand_home("~/.local/share/color-schemes")
+                .join(format!("{}.colors", self.scheme_name))
+        });
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, self.generated()?)?;
+        if !self.reload {
+            return Ok(Applied {
+                output: path,
+                command: None,
+                status: None,
+            });
+        }
+        let status = run(Command::new("plasma-apply-colorscheme").arg(&self.scheme_name))?;
+        Ok(Applied {
+            command: Some(format!("plasma-apply-colorscheme {}", self.scheme_name)),
+            output: path,
+            status: Some(status),
+        })
+    }
+
+    fn output(&mut self) -> Option<&PathBuf> {
+        self.output.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use test_utils::{create, state};
+
+    fn ayu() -> State {
+        state(
+            "ayu",
+            &[
+                ("background", Color::rgb(0x19, 0x1d, 0x26)),
+                ("foreground", Color::rgb(0xd0, 0xce, 0xc3)),
+                ("blue", Color::rgb(0x2d, 0x91, 0xd0)),
+                ("bright_black", Color::rgb(0x55, 0x55, 0x55)),
+            ],
+        )
+    }
+
+    #[test]
+    fn qt_palette() {
+        let qt5 = create(Qt::new("qt5ct"), &ayu(), "qt5ct { base #000000 }").unwrap();
+        let generated = qt5.generated().unwrap();
+        let active = generated.lines().nth(1).unwrap();
+        assert_eq!(active.split(", ").count(), 21);
+        assert!(active.starts_with("active_colors=#ffd0cec3, #ff2f3239, "));
+        assert!(active.contains(", #ff000000, #ff191d26, "));
+        let active: Vec<_> = active["active_colors=".len()..].split(", ").collect();
+        let disabled = generated.lines().nth(2).unwrap();
+        assert!(disabled.starts_with("disabled_colors="));
+        let disabled: Vec<_> = disabled["disabled_colors=".len()..].split(", ").collect();
+        assert_eq!(disabled.len(), active.len());
+        for (i, (active, disabled)) in active.iter().zip(&disabled).enumerate() {
+            match i {
+                0 | 6 | 7 | 8 => {
+                    assert_eq!((*active, *disabled), ("#ffd0cec3", "#ff555555"))
+                }
+                12 => assert_eq!((*active, *disabled), ("#ff2d91d0", "#ff2f3239")),
+                _ => assert_eq!(active, disabled),
+            }
+        }
+
+        let qt6 = create(Qt::new("qt6ct"), &ayu(), "qt6ct {}").unwrap();
+        let generated = qt6.generated().unwrap();
+        let active = generated.lines().nth(1).unwrap();
+        assert_eq!(active.split(", ").count(), 22);
+        assert!(active.ends_with(", #ff2d91d0"));
+    }
+
+    #[test]
+    fn kde_scheme() {
+        let kde = create(Kde::new(), &ayu(), "kde { positive #00ff00 }").unwrap();
+        let generated = kde.generated().unwrap();
+        assert!(generated.starts_with(
+            "[Colors:View]\nBackgroundNormal=25,29,38\nBackgroundAlternate=47,50,57\n"
+        ));
+        assert!(generated.contains("ForegroundPositive=0,255,0\n"));
+        assert!(generated.contains("ForegroundNegative=208,206,195\n"));
+        assert!(generated.contains(
+            "[Colors:Selection]\nBackgroundNormal=45,145,208\nBackgroundAlternate=45,145,208\nForegroundNormal=25,29,38\n"
+        ));
+        assert!(generated.contains("[General]\nColorScheme=ayu\nName=ayu\n"));
+    }
+}
@@ -0,0 +1,132 @@
+use super::{ui_roles, Applied, Color, Error, Getter, State, Theme};
+use config::map::Map;
+use config::Section;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use template::Format;
+
+const AVAILABLE_FIELDS: &[&str] = &[
+    "output",
+    "background",
+    "foreground",
+    "surface",
+    "inactive",
+    "accent",
+    "urgent",
+];
+
+/// Variables of rofi's default theme and the roles they refer to.
+const VARIABLES: &[(&str, &str)] = &[
+    ("normal-background", "background"),
+    ("normal-foreground", "foreground"),
+    ("alternate-normal-background", "surface"),
+    ("alternate-normal-foreground", "foreground"),
+    ("selected-normal-background", "accent"),
+    ("selected-normal-foreground", "background"),
+    ("active-background", "background"),
+    ("active-foreground", "accent"),
+    ("selected-active-background", "accent"),
+    ("selected-active-foreground", "background"),
+    ("urgent-background", "background"),
+    ("urgent-foreground", "urgent"),
+    ("selected-urgent-background", "urgent"),
+    ("selected-urgent-foreground", "background"),
+    ("border-color", "accent"),
+    ("separatorcolor", "surface"),
+];
+
+/// A `* { }` block of color variables, to `@import` from a `.rasi` theme.
+#[derive(Default, Debug)]
+pub struct Rofi {
+    output: Option<PathBuf>,
+    roles: Map<Color>,
+}
+
+impl Rofi {
+    pub fn new() -> Self {
+        Rofi::default()
+    }
+}
+
+impl Theme for Rofi {
+    fn name(&self) -> &str {
+        "rofi"
+    }
+
+    fn available_fields(&self) -> &[&str] {
+        AVAILABLE_FIELDS
+    }
+
+    fn create(&mut self, state: &State, section: &Section) -> Result<(), Error> {
+        self.output = section.get_path(state, "output").to_option();
+        self.roles = ui_roles(state, section)?;
+        Ok(())
+    }
+
+    fn generated(&self) -> Result<String, Error> {
+        let mut buf = vec!["* {".to_owned()];
+        for entry in &self.roles {
+            buf.push(format!(
+                "    {}: {};",
+                entry.name,
+                Format::HexAlpha.translucent(&entry.value)
+            ));
+        }
+        for (variable, role) in VARIABLES {
+            // Referencing an undefined variable is an error for rofi.
+            if self.roles.get(role).is_some() {
+                buf.push(format!("    {}: @{};", variable, role));
+            }
+        }
+        buf.push("}".to_owned());
+        Ok(buf.join("\n") + "\n")
+    }
+
+    fn apply(&self) -> Result<Applied, Error> {
+        let generated = self.generated()?;
+        let path = self
+            .output
+            .clone()
+            .unwrap_or_else(|| ::expand_home("~/.config/rofi/themer.rasi"));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        File::create(&path)?.write_all(generated.as_bytes())?;
+        // rofi reads its theme every time it starts.
+        Ok(Applied {
+            output: path,
+            command: None,
+            status: None,
+        })
+    }
+
+    fn output(&mut self) -> Option<&PathBuf> {
+        self.output.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use test_utils::{create, state};
+
+    #[test]
+    fn variables() {
+        let state = state(
+            "test",
+            &[
+                ("background", Color::rgb(0x19, 0x1d, 0x26)),
+                ("blue", Color::rgb(0x2d, 0x91, 0xd0)),
+            ],
+        );
+        let rofi = create(Rofi::new(), &state, "rofi { urgent #ff0000 }").unwrap();
+        let generated = rofi.generated().unwrap();
+        assert!(generated.starts_with(
+            "* {\n    background: #191d26;\n    accent: #2d91d0;\n    urgent: #ff0000;\n"
+        ));
+        assert!(generated.contains("    selected-normal-background: @accent;\n"));
+        assert!(!generated.contains("@foreground"));
+        assert!(!generated.contains("@surface"));
+    }
+}
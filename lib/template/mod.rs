@@ -1,45 +1,581 @@
 mod parser;
+mod registry;
 
 pub use self::parser::*;
+pub use self::registry::*;
 pub use super::Color;
+use super::{
+    expand_home, expect_color, Applied, Error as ThemeError, GetResult, Getter, State, Theme,
+};
 use config::map::Map;
+use config::Section;
+use config::Value;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
-pub fn process_parts(parts: Vec<Part>, map: &Map<Color>) -> Option<String> {
-    let mut result_vec = Vec::new();
-    'main: for part in parts {
+/// Inputs of a template that could not be resolved.
+#[derive(Debug, Fail)]
+pub struct MissingInput {
+    pub inputs: Vec<Input>,
+}
+
+impl fmt::Display for MissingInput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let inputs: Vec<String> = self
+            .inputs
+            .iter()
+            .map(|i| {
+                let exprs: Vec<String> = i.exprs.iter().map(|e| e.to_string()).collect();
+                format!("`{}` at line {}", exprs.join(" | "), i.line)
+            })
+            .collect();
+        write!(f, "missing inputs {}", inputs.join(", "))
+    }
+}
+
+/// Evaluate an expression to a color.
+fn eval(expr: &Expr, colors: &Map<Color>) -> Option<Color> {
+    let number = |i: usize| match expr {
+        Expr::Call(_, args) => match args.get(i) {
+            Some(Expr::Number(n)) => Some(*n),
+            _ => None,
+        },
+        _ => None,
+    };
+    match expr {
+        Expr::Name(name) => colors.get(name).cloned(),
+        Expr::Color(c) => Some(c.clone()),
+        Expr::Number(_) => None,
+        Expr::Call(function, args) => {
+            let c = eval(args.first()?, colors)?;
+            match function.as_str() {
+                "lighten" => Some(c.lighten(number(1)?)),
+                "darken" => Some(c.darken(number(1)?)),
+                "saturate" => Some(c.saturate(number(1)?)),
+                "desaturate" => Some(c.desaturate(number(1)?)),
+                "mix" => Some(c.mix(&eval(args.get(1)?, colors)?, number(2)?)),
+                "invert" => Some(c.invert()),
+                "alpha" => Some(c.with_alpha(number(1)?)),
+                _ => None,
+            }
+        }
+    }
+}
+
+fn resolve(input: &Input, colors: &Map<Color>, vars: &Map<String>) -> Option<String> {
+    for expr in &input.exprs {
+        if let Expr::Name(name) = expr {
+            if let Some(val) = vars.get(name) {
+                return Some(val.to_owned());
+            }
+        }
+        if let Some(c) = eval(expr, colors) {
+            return Some(input.format.format(&c));
+        }
+    }
+    let default = input.default.as_ref()?;
+    match Color::from_hex(default) {
+        Some(c) => Some(input.format.format(&c)),
+        None => Some(default.to_owned()),
+    }
+}
+
+/// Render parts, looking up inputs in `vars` first and then in `colors`.
+///
+/// Lines containing an unresolved optional input are removed.
+pub fn process_parts(
+    parts: &[Part],
+    colors: &Map<Color>,
+    vars: &Map<String>,
+) -> Result<String, MissingInput> {
+    let mut result = String::new();
+    let mut dropped = Vec::new();
+    let mut missing = Vec::new();
+    for part in parts {
         match part {
-            Part::Str(s) => result_vec.push(s),
-            Part::Input(inputs) => {
-                for input in &inputs {
-                    if let Some(val) = map.get(&input) {
-                        result_vec.push(format!("#{:02x}{:02x}{:02x}", val.0, val.1, val.2));
-                        continue 'main;
-                    }
-                }
-                println!("missing color, list of inputs : {:?}", inputs);
-                println!("{:#?}", map);
-                return None; // nothing found
+            Part::Str(s) => result.push_str(s),
+            Part::Input(input) => match resolve(input, colors, vars) {
+                Some(val) => result.push_str(&val),
+                None if input.optional => dropped.push(result.matches('\n').count()),
+                None => missing.push(input.clone()),
+            },
+        }
+    }
+    if !missing.is_empty() {
+        return Err(MissingInput { inputs: missing });
+    }
+    if dropped.is_empty() {
+        return Ok(result);
+    }
+    let lines: Vec<&str> = result
+        .split('\n')
+        .enumerate()
+        .filter(|(i, _)| !dropped.contains(i))
+        .map(|(_, line)| line)
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+pub fn read_template<P: AsRef<Path>>(path: P) -> Result<Template, ThemeError> {
+    let mut buf = String::new();
+    File::open(path.as_ref())?.read_to_string(&mut buf)?;
+    Parser::new(&buf)
+        .parse()
+        .map_err(|e| ThemeError::InvalidTemplate(path.as_ref().display().to_string(), e))
+}
+
+/// How a color is written by a template input.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// `#rrggbb`
+    #[default]
+    Hex,
+    /// `rrggbb`
+    HexBare,
+    /// `#RRGGBB`
+    HexUpper,
+    /// `#rrggbbaa`
+    HexAlpha,
+    /// `#aarrggbb`, as used by polybar
+    HexArgb,
+    /// `0xRRGGBB`
+    Hex0x,
+    /// `rgb(r,g,b)`
+    Rgb,
+    /// `rgba(r,g,b,a)` with alpha between 0 and 1
+    Rgba,
+    /// `r;g;b`, as used by ANSI escape sequences
+    Ansi,
+    /// `r,g,b` with components between 0 and 1
+    RgbFloat,
+    /// `rgb:rr/gg/bb`, as used by X11
+    X11,
+    /// Alpha between 0 and 1, e.g. for an opacity setting
+    Alpha,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "hex" => Some(Format::Hex),
+            "hex_bare" => Some(Format::HexBare),
+            "hex_upper" => Some(Format::HexUpper),
+            "hex_alpha" => Some(Format::HexAlpha),
+            "hex_argb" => Some(Format::HexArgb),
+            "hex0x" => Some(Format::Hex0x),
+            "rgb" => Some(Format::Rgb),
+            "rgba" => Some(Format::Rgba),
+            "ansi" => Some(Format::Ansi),
+            "rgbf" => Some(Format::RgbFloat),
+            "x11" => Some(Format::X11),
+            "alpha" => Some(Format::Alpha),
+            _ => None,
+        }
+    }
+
+    pub fn format(self, c: &Color) -> String {
+        let (r, g, b, a) = (c.red(), c.green(), c.blue(), c.alpha());
+        match self {
+            Format::Hex => format!("#{:02x}{:02x}{:02x}", r, g, b),
+            Format::HexBare => format!("{:02x}{:02x}{:02x}", r, g, b),
+            Format::HexUpper => format!("#{:02X}{:02X}{:02X}", r, g, b),
+            Format::HexAlpha => format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a),
+            Format::HexArgb => format!("#{:02x}{:02x}{:02x}{:02x}", a, r, g, b),
+            Format::Hex0x => format!("0x{:02X}{:02X}{:02X}", r, g, b),
+            Format::Rgb => format!("rgb({},{},{})", r, g, b),
+            Format::Rgba => format!("rgba({},{},{},{})", r, g, b, float(a)),
+            Format::Ansi => format!("{};{};{}", r, g, b),
+            Format::RgbFloat => format!("{},{},{}", float(r), float(g), float(b)),
+            Format::X11 => format!("rgb:{:02x}/{:02x}/{:02x}", r, g, b),
+            Format::Alpha => float(a),
+        }
+    }
+
+    /// `#rrggbb` for an opaque color and this format for a translucent one, for
+    /// programs which read both.
+    pub fn translucent(self, c: &Color) -> String {
+        if c.alpha() == 255 {
+            Format::Hex.format(c)
+        } else {
+            self.format(c)
+        }
+    }
+}
+
+/// Component between 0 and 1, with at most 3 decimals.
+fn float(c: u8) -> String {
+    let s = format!("{:.3}", f64::from(c) / 255.0);
+    s.trim_end_matches('0').trim_end_matches('.').to_owned()
+}
+
+/// Functions usable in template inputs, with their arguments: `c` for a color and
+/// `n` for a number.
+pub const FUNCTIONS: &[(&str, &str)] = &[
+    ("lighten", "cn"),
+    ("darken", "cn"),
+    ("saturate", "cn"),
+    ("desaturate", "cn"),
+    ("mix", "ccn"),
+    ("invert", "c"),
+    ("alpha", "cn"),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    /// Color or variable
+    Name(String),
+    Color(Color),
+    /// Number, percentages are already divided by 100
+    Number(f64),
+    /// Function from `FUNCTIONS` and its arguments
+    Call(String, Vec<Expr>),
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Name(name) => write!(f, "{}", name),
+            Expr::Color(c) => write!(f, "{}", Format::Hex.format(c)),
+            Expr::Number(n) => write!(f, "{}", n),
+            Expr::Call(function, args) => {
+                let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+                write!(f, "{}({})", function, args.join(", "))
             }
         }
     }
-    Some(result_vec.join(""))
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
+pub struct Input {
+    /// Expressions tried in order, the first one found is used
+    pub exprs: Vec<Expr>,
+    pub format: Format,
+    /// Value used if no name can be found
+    pub default: Option<String>,
+    /// Remove the line if no name can be found
+    pub optional: bool,
+    pub line: usize,
+}
+
+#[derive(Clone, Debug)]
 pub enum Part {
     Str(String),
-    Input(Vec<String>), // can have multiple fields
+    Input(Input),
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct TemplateHeader {
     pub name: Vec<String>,
     pub output: Option<Vec<Part>>,
     pub apply: Option<Vec<Part>>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Template {
     pub header: TemplateHeader,
     pub parts: Vec<Part>,
 }
+
+const AVAILABLE_FIELDS: &[&str] = &["template", "output", "apply"];
+
+/// Theme generated from a `.tt` template.
+///
+/// The header `output` and `apply` fields can use the `theme_name` variable,
+/// `apply` can also use `output`. Both can be overridden in the section.
+#[derive(Debug)]
+pub struct TemplateTheme {
+    name: String,
+    template: Template,
+    output: PathBuf,
+    apply: Option<String>,
+    generated: String,
+}
+
+impl TemplateTheme {
+    pub fn new(name: String, template: Template) -> Self {
+        TemplateTheme {
+            name,
+            template,
+            output: PathBuf::new(),
+            apply: None,
+            generated: String::new(),
+        }
+    }
+
+    fn render(
+        &self,
+        parts: &[Part],
+        colors: &Map<Color>,
+        vars: &Map<String>,
+    ) -> Result<String, ThemeError> {
+        process_parts(parts, colors, vars)
+            .map_err(|e| ThemeError::MissingInput(self.name.clone(), e))
+    }
+}
+
+impl Theme for TemplateTheme {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn available_fields(&self) -> &[&str] {
+        AVAILABLE_FIELDS
+    }
+
+    fn create(&mut self, state: &State, section: &Section) -> Result<(), ThemeError> {
+        // Colors from the section take precedence over the global ones.
+        let mut colors = Map::new();
+        for entry in section.values() {
+            let color = match entry.value {
+                Value::Str(ref s) => state.colors.get(s).cloned(),
+                ref v => expect_color(v).ok(),
+            };
+            if let Some(c) = color {
+                colors.insert(entry.name.to_owned(), c);
+            }
+        }
+        for entry in &state.colors {
+            colors.insert(entry.name.to_owned(), entry.value.to_owned());
+        }
+
+        let mut vars = Map::new();
+        vars.insert("theme_name".to_owned(), state.name.to_owned());
+        let output = match section.get_path(state, "output") {
+            GetResult::Ok(p) => p,
+            _ => match self.template.header.output {
+                Some(ref parts) => expand_home(self.render(parts, &colors, &vars)?.trim()),
+                None => ::std::env::temp_dir().join(format!("themer-{}_{}", self.name, state.name)),
+            },
+        };
+        vars.insert("output".to_owned(), output.display().to_string());
+        self.apply = match section.get_str(state, "apply") {
+            GetResult::Ok(s) => Some(s),
+            _ => match self.template.header.apply {
+                Some(ref parts) => Some(self.render(parts, &colors, &vars)?.trim().to_owned()),
+                None => None,
+            },
+        };
+        self.output = output;
+        self.generated = self.render(&self.template.parts, &colors, &vars)?;
+        Ok(())
+    }
+
+    fn generated(&self) -> Result<String, ThemeError> {
+        Ok(self.generated.clone())
+    }
+
+    fn apply(&self) -> Result<Applied, ThemeError> {
+        if let Some(parent) = self.output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        File::create(&self.output)?.write_all(self.generated.as_bytes())?;
+        let status = match self.apply {
+            Some(ref command) => Some(::run(Command::new("sh").arg("-c").arg(command))?),
+            None => None,
+        };
+        Ok(Applied {
+            output: self.output.clone(),
+            command: self.apply.clone(),
+            status,
+        })
+    }
+
+    fn output(&mut self) -> Option<&PathBuf> {
+        Some(&self.output)
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use test_utils::{create, state};
+
+    fn render(body: &str) -> Result<String, MissingInput> {
+        let template = Parser::new(&format!("---\nname: test\n---\n{}", body))
+            .parse()
+            .unwrap();
+        let colors = vec![("background".to_owned(), Color::rgb(0x19, 0x1d, 0x26))]
+            .into_iter()
+            .collect();
+        process_parts(&template.parts, &colors, &Map::new())
+    }
+
+    #[test]
+    fn default() {
+        assert_eq!(
+            render("bg={background}\ncursor={cursor | foreground ? #ffffff}").unwrap(),
+            "bg=#191d26\ncursor=#ffffff"
+        );
+    }
+
+    #[test]
+    fn optional() {
+        assert_eq!(
+            render("a={cursor ?}\nbg={background}\nb={cursor | foreground?}\n").unwrap(),
+            "bg=#191d26\n"
+        );
+    }
+
+    #[test]
+    fn missing() {
+        let err =
+            render("bg={background}\nfg={foreground}\ncursor={cursor | cursorColor}").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "missing inputs `foreground` at line 5, `cursor | cursorColor` at line 6"
+        );
+    }
+
+    #[test]
+    fn filters() {
+        assert_eq!(
+            render(
+                "{background:hex_bare} {background : hex_upper} {background:hex0x} \
+                 {background:rgb} {background:rgba} {background:ansi} {background:rgbf} \
+                 {background:x11} {cursor | foreground:rgbf ? #ffffff}"
+            )
+            .unwrap(),
+            "191d26 #191D26 0x191D26 rgb(25,29,38) rgba(25,29,38,1) 25;29;38 \
+             0.098,0.114,0.149 rgb:19/1d/26 1,1,1"
+        );
+    }
+
+    #[test]
+    fn unknown_filter() {
+        let err = Parser::new("---\nname: test\n---\n\n{background:hsl}")
+            .parse()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "unknown filter `hsl` at line 5");
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(
+            render(
+                "{lighten(background, 10%)} {darken(#808080, 0.5)} {mix(#000000, #ffffff, 25%)} \
+                 {invert(background)} {desaturate(#ff0000, 100%)} {alpha(#ffffff, 0.5):rgba} \
+                 {lighten(cursor, 5%) | mix(background, #ffffff, 0.1):rgb}"
+            )
+            .unwrap(),
+            "#2d3445 #000000 #404040 #e6e2d9 #808080 rgba(255,255,255,0.502) rgb(48,52,60)"
+        );
+    }
+
+    #[test]
+    fn invalid_function() {
+        let parse = |body: &str| {
+            Parser::new(&format!("---\nname: test\n---\n{}", body))
+                .parse()
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(parse("{shade(red)}"), "unknown function `shade` at line 4");
+        assert_eq!(
+            parse("{lighten(red)}"),
+            "invalid arguments for `lighten` at line 4"
+        );
+        assert_eq!(
+            parse("{mix(red, 10%, blue)}"),
+            "invalid arguments for `mix` at line 4"
+        );
+    }
+
+    #[test]
+    fn alpha() {
+        assert_eq!(
+            render(
+                "{#191d26cc:hex_alpha} {#191d26cc:hex_argb} {#191d26cc:alpha} {#1238:rgba} \
+                 {alpha(background, 25%):hex_alpha} {lighten(#00000080, 50%):hex_alpha}"
+            )
+            .unwrap(),
+            "#191d26cc #cc191d26 0.8 rgba(17,34,51,0.533) #191d2640 #80808080"
+        );
+    }
+
+    #[test]
+    fn translucent() {
+        let opaque = Color::rgb(0x19, 0x1d, 0x26);
+        let translucent = Color::rgba(0x19, 0x1d, 0x26, 0xcc);
+        assert_eq!(Format::HexAlpha.translucent(&opaque), "#191d26");
+        assert_eq!(Format::HexAlpha.translucent(&translucent), "#191d26cc");
+        assert_eq!(Format::HexArgb.translucent(&translucent), "#cc191d26");
+    }
+
+    fn theme(header: &str, body: &str, section: &str) -> Result<TemplateTheme, ThemeError> {
+        let state = state(
+            "ayu",
+            &[
+                ("background", Color::rgb(0x19, 0x1d, 0x26)),
+                ("foreground", Color::rgb(0xd0, 0xce, 0xc3)),
+            ],
+        );
+        let template = Parser::new(&format!("---\nname: test\n{}---\n{}", header, body))
+            .parse()
+            .unwrap();
+        create(
+            TemplateTheme::new("test".to_owned(), template),
+            &state,
+            section,
+        )
+    }
+
+    #[test]
+    fn section_colors() {
+        let theme = theme(
+            "",
+            "bg={background} fg={foreground} accent={accent}",
+            "test { background #ff0000 accent foreground }",
+        )
+        .unwrap();
+        assert_eq!(
+            theme.generated().unwrap(),
+            "bg=#ff0000 fg=#d0cec3 accent=#d0cec3"
+        );
+        assert!(theme.apply.is_none());
+    }
+
+    #[test]
+    fn section_output() {
+        let mut theme = theme(
+            "output: ~/.xres/{theme_name}\napply: xrdb -merge {output}\n",
+            "{background}",
+            "test { output /tmp/ayu.xres }",
+        )
+        .unwrap();
+        assert_eq!(theme.output(), Some(&PathBuf::from("/tmp/ayu.xres")));
+        assert_eq!(theme.apply.as_ref().unwrap(), "xrdb -merge /tmp/ayu.xres");
+    }
+
+    #[test]
+    fn apply_template() {
+        let dir = ::std::env::temp_dir().join(format!("themer-template-{}", ::std::process::id()));
+        let theme = theme(
+            &format!(
+                "output: {}/{{theme_name}}.conf\napply: cp {{output}} {{output}}.bak\n",
+                dir.display()
+            ),
+            "background = {background}\n",
+            "test {}",
+        )
+        .unwrap();
+        let applied = theme.apply().unwrap();
+        assert!(applied.success());
+        assert_eq!(applied.output, dir.join("ayu.conf"));
+        assert_eq!(
+            applied.command.unwrap(),
+            format!("cp {0}/ayu.conf {0}/ayu.conf.bak", dir.display())
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("ayu.conf")).unwrap(),
+            "background = #191d26\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("ayu.conf.bak")).unwrap(),
+            "background = #191d26\n"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,4 +1,4 @@
-use super::{Part, Template, TemplateHeader};
+use super::{Color, Expr, Format, Input, Part, Template, TemplateHeader, FUNCTIONS};
 use std::iter::Peekable;
 use std::str::Chars;
 
@@ -6,18 +6,28 @@ use std::str::Chars;
 pub enum Error {
     #[fail(display = "invalid header")]
     InvalidHeader,
-    #[fail(display = "invalid input field")]
-    InvalidInput,
+    #[fail(display = "unknown header field `{}` at line {}", _0, _1)]
+    UnknownField(String, usize),
+    #[fail(display = "invalid input field at line {}", _0)]
+    InvalidInput(usize),
+    #[fail(display = "unknown filter `{}` at line {}", _0, _1)]
+    UnknownFilter(String, usize),
+    #[fail(display = "unknown function `{}` at line {}", _0, _1)]
+    UnknownFunction(String, usize),
+    #[fail(display = "invalid arguments for `{}` at line {}", _0, _1)]
+    InvalidArguments(String, usize),
 }
 
 pub struct Parser<'a> {
     buf: Peekable<Chars<'a>>,
+    line: usize,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(buf: &'a str) -> Parser<'a> {
         Parser {
             buf: buf.chars().peekable(),
+            line: 1,
         }
     }
 
@@ -26,7 +36,11 @@ impl<'a> Parser<'a> {
     }
 
     fn next(&mut self) -> Option<char> {
-        self.buf.next()
+        let c = self.buf.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
     }
 
     fn take(&mut self, count: usize) -> String {
@@ -74,33 +88,130 @@ impl<'a> Parser<'a> {
         vec
     }
 
+    /// Parse `{expr | other : filter ? default}`, a `?` without default makes the input optional.
     fn parse_input(&mut self) -> Result<Part, Error> {
         self.next();
-        let mut inputs = Vec::new();
+        let mut input = Input {
+            exprs: Vec::new(),
+            format: Format::default(),
+            default: None,
+            optional: false,
+            line: self.line,
+        };
         loop {
+            input.exprs.push(self.parse_expr()?);
+            if self.peek() != Some('|') {
+                break;
+            }
+            self.next();
+        }
+        if self.peek() == Some(':') {
+            self.next();
             self.skip_whitespace();
-            let mut buf = String::new();
+            let mut filter = String::new();
             while self
                 .peek()
-                .map(|c| c != '}' && c != '|' && !c.is_whitespace())
+                .map(|c| c.is_alphanumeric() || c == '_')
                 .unwrap_or(false)
             {
-                buf.push(self.next().unwrap());
+                filter.push(self.next().unwrap());
             }
-            inputs.push(buf);
+            input.format = match Format::from_name(&filter) {
+                Some(format) => format,
+                None => return Err(Error::UnknownFilter(filter, self.line)),
+            };
             self.skip_whitespace();
-            match self.peek() {
-                Some('}') => {
+        }
+        if self.peek() == Some('?') {
+            self.next();
+            let mut default = String::new();
+            while self.peek().map(|c| c != '}' && c != '\n').unwrap_or(false) {
+                default.push(self.next().unwrap());
+            }
+            let default = default.trim();
+            if default.is_empty() {
+                input.optional = true;
+            } else {
+                input.default = Some(default.to_owned());
+            }
+        }
+        if self.next() != Some('}') {
+            return Err(Error::InvalidInput(self.line));
+        }
+        Ok(Part::Input(input))
+    }
+
+    fn take_while<F: Fn(char) -> bool>(&mut self, f: F) -> String {
+        let mut buf = String::new();
+        while self.peek().map(&f).unwrap_or(false) {
+            buf.push(self.next().unwrap());
+        }
+        buf
+    }
+
+    /// Parse a name, a `#rrggbb` color, a number or a function call.
+    fn parse_expr(&mut self) -> Result<Expr, Error> {
+        self.skip_whitespace();
+        let expr = match self.peek() {
+            Some('#') => {
+                self.next();
+                let hex = self.take_while(|c| c.is_ascii_hexdigit());
+                Expr::Color(Color::from_hex(&hex).ok_or(Error::InvalidInput(self.line))?)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let number = self.take_while(|c| c.is_ascii_digit() || c == '.');
+                let number: f64 = number.parse().map_err(|_| Error::InvalidInput(self.line))?;
+                if self.peek() == Some('%') {
                     self.next();
-                    break;
+                    Expr::Number(number / 100.0)
+                } else {
+                    Expr::Number(number)
                 }
-                Some('|') => {
-                    self.next();
+            }
+            _ => {
+                let name = self.take_while(|c| !"}|:?(),".contains(c) && !c.is_whitespace());
+                if name.is_empty() {
+                    return Err(Error::InvalidInput(self.line));
+                }
+                self.skip_whitespace();
+                if self.peek() == Some('(') {
+                    self.parse_call(name)?
+                } else {
+                    Expr::Name(name)
                 }
-                _ => return Err(Error::InvalidInput),
+            }
+        };
+        self.skip_whitespace();
+        Ok(expr)
+    }
+
+    fn parse_call(&mut self, function: String) -> Result<Expr, Error> {
+        let signature = match FUNCTIONS.iter().find(|(name, _)| *name == function) {
+            Some((_, signature)) => signature,
+            None => return Err(Error::UnknownFunction(function, self.line)),
+        };
+        self.next();
+        let mut args = Vec::new();
+        loop {
+            args.push(self.parse_expr()?);
+            match self.next() {
+                Some(',') => continue,
+                Some(')') => break,
+                _ => return Err(Error::InvalidInput(self.line)),
             }
         }
-        Ok(Part::Input(inputs))
+        let valid = args.len() == signature.len()
+            && args
+                .iter()
+                .zip(signature.chars())
+                .all(|(arg, kind)| match arg {
+                    Expr::Number(_) => kind == 'n',
+                    _ => kind == 'c',
+                });
+        if !valid {
+            return Err(Error::InvalidArguments(function, self.line));
+        }
+        Ok(Expr::Call(function, args))
     }
 
     fn parse_line(&mut self) -> Result<Vec<Part>, Error> {
@@ -149,10 +260,8 @@ impl<'a> Parser<'a> {
                 "name" => header.name = self.parse_ident_list(','),
                 "output" => header.output = Some(self.parse_line()?),
                 "apply" => header.apply = Some(self.parse_line()?),
-                i if i.is_empty() => return Err(Error::InvalidHeader),
-                i => {
-                    println!("unknown header field {}", i);
-                }
+                "" => return Err(Error::InvalidHeader),
+                i => return Err(Error::UnknownField(i.to_owned(), self.line)),
             }
             self.skip_whitespace();
         }
@@ -0,0 +1,205 @@
+use super::{read_template, Template};
+use config::map::Map;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use Error;
+
+/// Name clash or broken template found while scanning template directories.
+#[derive(Debug)]
+pub enum Conflict {
+    /// A template could not be read or parsed, it is skipped.
+    Invalid { path: PathBuf, error: Error },
+    /// Two templates of the same directory share a name, the first one is used.
+    Duplicate {
+        name: String,
+        used: PathBuf,
+        ignored: PathBuf,
+    },
+    /// A template of a directory with higher priority hides another one.
+    Shadowed {
+        name: String,
+        used: PathBuf,
+        ignored: PathBuf,
+    },
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Conflict::Invalid { path, error } => {
+                write!(f, "skipping template {}: {}", path.display(), error)
+            }
+            Conflict::Duplicate {
+                name,
+                used,
+                ignored,
+            } => write!(
+                f,
+                "duplicate template `{}`: using {}, ignoring {}",
+                name,
+                used.display(),
+                ignored.display()
+            ),
+            Conflict::Shadowed {
+                name,
+                used,
+                ignored,
+            } => write!(
+                f,
+                "template `{}` from {} shadows {}",
+                name,
+                used.display(),
+                ignored.display()
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Registered {
+    dir: PathBuf,
+    path: PathBuf,
+    template: Template,
+}
+
+/// Templates indexed by every name of their header.
+#[derive(Debug, Default)]
+pub struct Registry {
+    templates: Map<Registered>,
+    conflicts: Vec<Conflict>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry::default()
+    }
+
+    /// Scan directories by decreasing priority, missing ones are skipped.
+    pub fn scan_dirs<P: AsRef<Path>>(dirs: &[P]) -> Result<Self, Error> {
+        let mut registry = Registry::new();
+        for dir in dirs {
+            if dir.as_ref().is_dir() {
+                registry.scan(dir)?;
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Register every `.tt` file of `dir`, names already registered are kept and
+    /// broken templates are reported as conflicts.
+    pub fn scan<P: AsRef<Path>>(&mut self, dir: P) -> Result<(), Error> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir.as_ref())? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().map(|e| e == "tt").unwrap_or(false) {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        for path in paths {
+            match read_template(&path) {
+                Ok(template) => self.insert(dir.as_ref(), path, template),
+                Err(error) => self.conflicts.push(Conflict::Invalid { path, error }),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn insert(&mut self, dir: &Path, path: PathBuf, template: Template) {
+        for name in &template.header.name {
+            let name = name.to_lowercase();
+            if let Some(registered) = self.templates.get(&name) {
+                let conflict = if registered.dir == dir {
+                    Conflict::Duplicate {
+                        name,
+                        used: registered.path.clone(),
+                        ignored: path.clone(),
+                    }
+                } else {
+                    Conflict::Shadowed {
+                        name,
+                        used: registered.path.clone(),
+                        ignored: path.clone(),
+                    }
+                };
+                self.conflicts.push(conflict);
+                continue;
+            }
+            self.templates.insert(
+                name,
+                Registered {
+                    dir: dir.to_owned(),
+                    path: path.clone(),
+                    template: template.clone(),
+                },
+            );
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Template> {
+        self.templates
+            .get(&name.to_lowercase())
+            .map(|r| &r.template)
+    }
+
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::super::Parser;
+    use super::*;
+
+    fn template(names: &str) -> Template {
+        Parser::new(&format!("---\nname: {}\n---\n", names))
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn conflicts() {
+        let mut registry = Registry::new();
+        let user = Path::new("/user");
+        let system = Path::new("/system");
+        registry.insert(user, user.join("x11.tt"), template("x11,xresources"));
+        registry.insert(user, user.join("xres.tt"), template("xresources"));
+        registry.insert(system, system.join("x11.tt"), template("x11"));
+        registry.insert(system, system.join("kitty.tt"), template("kitty"));
+
+        assert!(registry.get("X11").is_some());
+        assert!(registry.get("kitty").is_some());
+        assert!(registry.get("urxvt").is_none());
+        let conflicts: Vec<String> = registry.conflicts().iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            conflicts,
+            vec![
+                "duplicate template `xresources`: using /user/x11.tt, ignoring /user/xres.tt",
+                "template `x11` from /user/x11.tt shadows /system/x11.tt",
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_templates_skipped() {
+        let dir = ::std::env::temp_dir().join(format!("themer-registry-{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.tt"), "---\nname: a\nouptut: ~/a\n---\n").unwrap();
+        fs::write(dir.join("b.tt"), "---\nname: b\n---\n").unwrap();
+        let registry = Registry::scan_dirs(&[&dir]).unwrap();
+        assert!(registry.get("a").is_none());
+        assert!(registry.get("b").is_some());
+        let conflicts: Vec<String> = registry.conflicts().iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            conflicts,
+            vec![format!(
+                "skipping template {}: {}: unknown header field `ouptut` at line 3",
+                dir.join("a.tt").display(),
+                dir.join("a.tt").display()
+            )]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,244 @@
+use super::{run, splice_file, ui_roles, Applied, Color, Error, GetResult, Getter, State, Theme};
+use config::map::Map;
+use config::Section;
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use template::Format;
+
+const AVAILABLE_FIELDS: &[&str] = &[
+    "output",
+    "splice",
+    "reload",
+    "background",
+    "foreground",
+    "surface",
+    "inactive",
+    "accent",
+    "urgent",
+];
+
+/// Style options with their background, foreground and attributes.
+const STYLES: &[(&str, Option<&str>, &str, &str)] = &[
+    ("status-style", Some("background"), "foreground", ""),
+    ("window-status-style", Some("background"), "inactive", ""),
+    (
+        "window-status-current-style",
+        Some("accent"),
+        "background",
+        "bold",
+    ),
+    ("window-status-bell-style", Some("urgent"), "background", ""),
+    ("pane-border-style", None, "surface", ""),
+    ("pane-active-border-style", None, "accent", ""),
+    ("message-style", Some("surface"), "foreground", ""),
+    ("message-command-style", Some("surface"), "foreground", ""),
+    ("mode-style", Some("accent"), "background", ""),
+];
+
+/// Colour options with their role.
+const COLOURS: &[(&str, &str)] = &[
+    ("clock-mode-colour", "accent"),
+    ("display-panes-colour", "inactive"),
+    ("display-panes-active-colour", "accent"),
+];
+
+/// Status line and pane styles for tmux, sourced by every running server.
+#[derive(Debug)]
+pub struct Tmux {
+    /// File to `source-file` from `tmux.conf`
+    output: Option<PathBuf>,
+    /// Existing config in which the options are spliced
+    splice: Option<PathBuf>,
+    /// Source the file in the running servers once written, on by default
+    reload: bool,
+    roles: Map<Color>,
+}
+
+impl Tmux {
+    pub fn new() -> Self {
+        Tmux {
+            output: None,
+            splice: None,
+            reload: true,
+            roles: Map::new(),
+        }
+    }
+}
+
+/// Sockets of the tmux servers of the user which accept connections, stale ones
+/// are left out.
+fn server_sockets() -> Result<Vec<PathBuf>, Error> {
+    let uid = fs::metadata("/proc/self")?.uid();
+    let dir = env::var_os("TMUX_TMPDIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(format!("tmux-{}", uid));
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(ref e) if e.kind() == ::std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut sockets = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        // An entry removed since the directory was read is skipped like a stale one.
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        if metadata.file_type().is_socket() && UnixStream::connect(&path).is_ok() {
+            sockets.push(path);
+        }
+    }
+    sockets.sort();
+    Ok(sockets)
+}
+
+fn source_file(socket: &Path, path: &Path) -> Result<ExitStatus, Error> {
+    run(Command::new("tmux")
+        .arg("-S")
+        .arg(socket)
+        .arg("source-file")
+        .arg(path))
+}
+
+impl Theme for Tmux {
+    fn name(&self) -> &str {
+        "tmux"
+    }
+
+    fn available_fields(&self) -> &[&str] {
+        AVAILABLE_FIELDS
+    }
+
+    fn create(&mut self, state: &State, section: &Section) -> Result<(), Error> {
+        self.output = section.get_path(state, "output").to_option();
+        self.splice = section.get_path(state, "splice").to_option();
+        if let GetResult::Ok(reload) = section.get_bool(state, "reload") {
+            self.reload = reload;
+        }
+        self.roles = ui_roles(state, section)?;
+        Ok(())
+    }
+
+    fn generated(&self) -> Result<String, Error> {
+        let mut buf = Vec::new();
+        for (option, bg, fg, attributes) in STYLES {
+            let fg = match self.roles.get(fg) {
+                Some(fg) => format!("fg={}", Format::Hex.format(fg)),
+                None => continue,
+            };
+            let mut style = match bg {
+                Some(bg) => match self.roles.get(bg) {
+                    Some(bg) => vec![format!("bg={}", Format::Hex.format(bg)), fg],
+                    None => continue,
+                },
+                None => vec![fg],
+            };
+            if !attributes.is_empty() {
+                style.push(attributes.to_string());
+            }
+            buf.push(format!("set -g {} \"{}\"", option, style.join(",")));
+        }
+        for (option, role) in COLOURS {
+            if let Some(c) = self.roles.get(role) {
+                buf.push(format!("set -g {} \"{}\"", option, Format::Hex.format(c)));
+            }
+        }
+        Ok(buf.join("\n") + "\n")
+    }
+
+    fn apply(&self) -> Result<Applied, Error> {
+        let generated = self.generated()?;
+        let path = match self.splice {
+            Some(ref path) => {
+                splice_file(path, &generated, "#")?;
+                path.to_owned()
+            }
+            None => {
+                let path = self
+                    .output
+                    .clone()
+                    .unwrap_or_else(|| ::expand_home("~/.config/tmux/themer.conf"));
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                File::create(&path)?.write_all(generated.as_bytes())?;
+                path
+            }
+        };
+        let sockets = if self.reload {
+            server_sockets()?
+        } else {
+            Vec::new()
+        };
+        if sockets.is_empty() {
+            return Ok(Applied {
+                output: path,
+                command: None,
+                status: None,
+            });
+        }
+        // The first failure is reported, the other servers are still reloaded.
+        let mut status: Option<ExitStatus> = None;
+        for socket in &sockets {
+            let s = source_file(socket, &path)?;
+            if status.map(|s| s.success()).unwrap_or(true) {
+                status = Some(s);
+            }
+        }
+        Ok(Applied {
+            command: Some(format!("tmux source-file {}", path.display())),
+            output: path,
+            status,
+        })
+    }
+
+    fn output(&mut self) -> Option<&PathBuf> {
+        self.output.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use test_utils::{create, state};
+
+    #[test]
+    fn styles() {
+        let state = state(
+            "test",
+            &[
+                ("background", Color::rgb(0x19, 0x1d, 0x26)),
+                ("foreground", Color::rgb(0xd0, 0xce, 0xc3)),
+                ("blue", Color::rgb(0x2d, 0x91, 0xd0)),
+            ],
+        );
+        assert!(
+            !create(Tmux::new(), &state, "tmux { reload no }")
+                .unwrap()
+                .reload
+        );
+        let tmux = create(Tmux::new(), &state, "tmux {}").unwrap();
+        assert!(tmux.reload);
+        assert_eq!(
+            tmux.generated().unwrap(),
+            "\
+set -g status-style \"bg=#191d26,fg=#d0cec3\"
+set -g window-status-current-style \"bg=#2d91d0,fg=#191d26,bold\"
+set -g pane-border-style \"fg=#2f3239\"
+set -g pane-active-border-style \"fg=#2d91d0\"
+set -g message-style \"bg=#2f3239,fg=#d0cec3\"
+set -g message-command-style \"bg=#2f3239,fg=#d0cec3\"
+set -g mode-style \"bg=#2d91d0,fg=#191d26\"
+set -g clock-mode-colour \"#2d91d0\"
+set -g display-panes-active-colour \"#2d91d0\"
+"
+        );
+    }
+}
@@ -0,0 +1,402 @@
+use super::{
+    derive_shades, editor_roles, slug, Applied, Color, Error, GetResult, Getter, State, Theme,
+};
+use config::map::Map;
+use config::Section;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use x11::{mapped_color, ANSI};
+
+/// Roles used on top of `EDITOR_ROLES`, for diagnostics and diffs.
+const ROLES: &[(&str, &[&str])] = &[
+    ("special", &["cyan"]),
+    ("info", &["blue"]),
+    ("hint", &["cyan"]),
+    ("added", &["green"]),
+    ("changed", &["yellow"]),
+    ("removed", &["red"]),
+];
+
+const AVAILABLE_FIELDS: &[&str] = &[
+    "output",
+    "format",
+    "bg",
+    "fg",
+    "cursor",
+    "surface",
+    "selection",
+    "comment",
+    "keyword",
+    "string",
+    "number",
+    "function",
+    "type",
+    "variable",
+    "operator",
+    "special",
+    "error",
+    "warning",
+    "info",
+    "hint",
+    "added",
+    "changed",
+    "removed",
+];
+
+/// Highlight groups with their foreground role, background role and style.
+const GROUPS: &[(&str, &str, &str, &str)] = &[
+    ("Normal", "fg", "bg", ""),
+    ("NormalFloat", "fg", "surface", ""),
+    ("Cursor", "bg", "cursor", ""),
+    ("CursorLine", "", "surface", ""),
+    ("CursorLineNr", "fg", "", "bold"),
+    ("LineNr", "comment", "", ""),
+    ("SignColumn", "", "bg", ""),
+    ("Visual", "", "selection", ""),
+    ("Search", "bg", "warning", ""),
+    ("IncSearch", "bg", "number", ""),
+    ("MatchParen", "", "selection", "bold"),
+    ("StatusLine", "fg", "surface", ""),
+    ("StatusLineNC", "comment", "surface", ""),
+    ("VertSplit", "surface", "", ""),
+    ("WinSeparator", "surface", "", ""),
+    ("TabLine", "comment", "surface", ""),
+    ("TabLineSel", "fg", "bg", "bold"),
+    ("TabLineFill", "", "surface", ""),
+    ("Pmenu", "fg", "surface", ""),
+    ("PmenuSel", "bg", "function", ""),
+    ("Folded", "comment", "surface", ""),
+    ("Title", "function", "", "bold"),
+    ("Directory", "function", "", ""),
+    ("NonText", "comment", "", ""),
+    ("ErrorMsg", "error", "", "bold"),
+    ("WarningMsg", "warning", "", ""),
+    ("Comment", "comment", "", "italic"),
+    ("Constant", "number", "", ""),
+    ("String", "string", "", ""),
+    ("Character", "string", "", ""),
+    ("Number", "number", "", ""),
+    ("Boolean", "number", "", ""),
+    ("Identifier", "variable", "", ""),
+    ("Function", "function", "", ""),
+    ("Statement", "keyword", "", ""),
+    ("Keyword", "keyword", "", ""),
+    ("Operator", "operator", "", ""),
+    ("PreProc", "keyword", "", ""),
+    ("Type", "type", "", ""),
+    ("Special", "special", "", ""),
+    ("Delimiter", "operator", "", ""),
+    ("Underlined", "function", "", "underline"),
+    ("Error", "error", "", "bold"),
+    ("Todo", "warning", "", "bold"),
+    ("DiffAdd", "added", "", ""),
+    ("DiffChange", "changed", "", ""),
+    ("DiffDelete", "removed", "", ""),
+    ("DiffText", "bg", "changed", ""),
+    ("DiagnosticError", "error", "", ""),
+    ("DiagnosticWarn", "warning", "", ""),
+    ("DiagnosticInfo", "info", "", ""),
+    ("DiagnosticHint", "hint", "", ""),
+];
+
+/// Treesitter captures and the highlight groups they are linked to.
+const LINKS: &[(&str, &str)] = &[
+    ("@comment", "Comment"),
+    ("@string", "String"),
+    ("@string.escape", "Special"),
+    ("@character", "Character"),
+    ("@number", "Number"),
+    ("@number.float", "Number"),
+    ("@boolean", "Boolean"),
+    ("@constant", "Constant"),
+    ("@constant.builtin", "Special"),
+    ("@function", "Function"),
+    ("@function.builtin", "Special"),
+    ("@function.call", "Function"),
+    ("@function.method", "Function"),
+    ("@constructor", "Type"),
+    ("@keyword", "Keyword"),
+    ("@keyword.function", "Keyword"),
+    ("@keyword.return", "Keyword"),
+    ("@keyword.conditional", "Keyword"),
+    ("@keyword.repeat", "Keyword"),
+    ("@operator", "Operator"),
+    ("@type", "Type"),
+    ("@type.builtin", "Type"),
+    ("@variable", "Identifier"),
+    ("@variable.builtin", "Special"),
+    ("@variable.parameter", "Identifier"),
+    ("@property", "Identifier"),
+    ("@punctuation", "Delimiter"),
+    ("@tag", "Keyword"),
+    ("@markup.heading", "Title"),
+    ("@markup.link", "Underlined"),
+    ("@diff.plus", "DiffAdd"),
+    ("@diff.minus", "DiffDelete"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Vim,
+    Lua,
+}
+
+#[derive(Debug)]
+pub struct Vim {
+    /// `vim` or `neovim`, the latter defaulting to a Lua colorscheme
+    program: String,
+    /// Name of the colorscheme, the one of the theme made safe for paths and strings
+    colors_name: String,
+    format: Format,
+    output: Option<PathBuf>,
+    terminal: Vec<(usize, Color)>,
+    roles: Map<Color>,
+}
+
+impl Vim {
+    pub fn new(program: &str) -> Self {
+        let neovim = program == "neovim" || program == "nvim";
+        Vim {
+            program: program.to_owned(),
+            colors_name: String::new(),
+            format: if neovim { Format::Lua } else { Format::Vim },
+            output: None,
+            terminal: Vec::new(),
+            roles: Map::new(),
+        }
+    }
+
+    fn path(&self) -> PathBuf {
+        self.output.clone().unwrap_or_else(|| match self.format {
+            Format::Vim => ::expand_home("~/.vim/colors").join(format!("{}.vim", self.colors_name)),
+            Format::Lua => {
+                ::expand_home("~/.config/nvim/colors").join(format!("{}.lua", self.colors_name))
+            }
+        })
+    }
+
+    /// `dark` unless the background contrasts more with black than with white.
+    fn background(&self) -> &str {
+        match self.roles.get("bg") {
+            Some(bg)
+                if bg.contrast(&Color::rgb(0, 0, 0)) > bg.contrast(&Color::rgb(255, 255, 255)) =>
+            {
+                "light"
+            }
+            _ => "dark",
+        }
+    }
+}
+
+impl Theme for Vim {
+    fn name(&self) -> &str {
+        &self.program
+    }
+
+    fn available_fields(&self) -> &[&str] {
+        AVAILABLE_FIELDS
+    }
+
+    fn create(&mut self, state: &State, section: &Section) -> Result<(), Error> {
+        self.colors_name = slug(&state.name)?;
+        self.output = section.get_path(state, "output").to_option();
+        match section.get_str(state, "format") {
+            GetResult::Ok(ref f) if f == "lua" => self.format = Format::Lua,
+            GetResult::Ok(_) => self.format = Format::Vim,
+            _ => {
+                if let Some(ref output) = self.output {
+                    if output.extension().map(|e| e == "lua").unwrap_or(false) {
+                        self.format = Format::Lua;
+                    }
+                }
+            }
+        }
+        for (i, name) in ANSI.iter().enumerate() {
+            if let Some(c) = mapped_color(state, section, name) {
+                self.terminal.push((i, c));
+            }
+        }
+        self.roles = editor_roles(state, section, ROLES)?;
+        derive_shades(&mut self.roles, &[]);
+        Ok(())
+    }
+
+    fn generated(&self) -> Result<String, Error> {
+        let hex = ::template::Format::Hex;
+        let role = |name: &str| self.roles.get(name);
+        let mut buf = Vec::new();
+        match self.format {
+            Format::Vim => {
+                buf.push("\" Generated by themer".to_owned());
+                buf.push("hi clear".to_owned());
+                buf.push("if exists(\"syntax_on\")".to_owned());
+                buf.push("  syntax reset".to_owned());
+                buf.push("endif".to_owned());
+                buf.push(format!("set background={}", self.background()));
+                buf.push(format!("let g:colors_name = \"{}\"", self.colors_name));
+                buf.push(String::new());
+                for (i, c) in &self.terminal {
+                    buf.push(format!(
+                        "let g:terminal_color_{} = \"{}\"",
+                        i,
+                        hex.format(c)
+                    ));
+                }
+                buf.push(String::new());
+                for (group, fg, bg, style) in GROUPS {
+                    let mut line = format!("hi {}", group);
+                    if let Some(c) = role(fg) {
+                        line.push_str(&format!(
+                            " guifg={} ctermfg={}",
+                            hex.format(c),
+                            c.to_ansi256()
+                        ));
+                    }
+                    if let Some(c) = role(bg) {
+                        line.push_str(&format!(
+                            " guibg={} ctermbg={}",
+                            hex.format(c),
+                            c.to_ansi256()
+                        ));
+                    }
+                    let style = if style.is_empty() { "NONE" } else { style };
+                    line.push_str(&format!(" gui={} cterm={}", style, style));
+                    buf.push(line);
+                }
+                buf.push(String::new());
+                for (capture, group) in LINKS {
+                    buf.push(format!("hi! link {} {}", capture, group));
+                }
+            }
+            Format::Lua => {
+                buf.push("-- Generated by themer".to_owned());
+                buf.push("vim.cmd(\"highlight clear\")".to_owned());
+                buf.push("if vim.fn.exists(\"syntax_on\") == 1 then".to_owned());
+                buf.push("  vim.cmd(\"syntax reset\")".to_owned());
+                buf.push("end".to_owned());
+                buf.push(format!("vim.o.background = \"{}\"", self.background()));
+                buf.push(format!("vim.g.colors_name = \"{}\"", self.colors_name));
+                buf.push(String::new());
+                for (i, c) in &self.terminal {
+                    buf.push(format!(
+                        "vim.g.terminal_color_{} = \"{}\"",
+                        i,
+                        hex.format(c)
+                    ));
+                }
+                buf.push(String::new());
+                buf.push("local hl = function(group, spec)".to_owned());
+                buf.push("  vim.api.nvim_set_hl(0, group, spec)".to_owned());
+                buf.push("end".to_owned());
+                buf.push(String::new());
+                for (group, fg, bg, style) in GROUPS {
+                    let mut spec = Vec::new();
+                    if let Some(c) = role(fg) {
+                        spec.push(format!("fg = \"{}\"", hex.format(c)));
+                    }
+                    if let Some(c) = role(bg) {
+                        spec.push(format!("bg = \"{}\"", hex.format(c)));
+                    }
+                    if !style.is_empty() {
+                        spec.push(format!("{} = true", style));
+                    }
+                    buf.push(format!("hl(\"{}\", {{ {} }})", group, spec.join(", ")));
+                }
+                buf.push(String::new());
+                for (capture, group) in LINKS {
+                    buf.push(format!("hl(\"{}\", {{ link = \"{}\" }})", capture, group));
+                }
+            }
+        }
+        Ok(buf.join("\n") + "\n")
+    }
+
+    fn apply(&self) -> Result<Applied, Error> {
+        let generated = self.generated()?;
+        let path = self.path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        File::create(&path)?.write_all(generated.as_bytes())?;
+        Ok(Applied {
+            output: path,
+            command: None,
+            status: None,
+        })
+    }
+
+    fn output(&mut self) -> Option<&PathBuf> {
+        self.output.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use test_utils::{create, state};
+
+    fn scheme(program: &str, section: &str) -> Result<Vim, Error> {
+        let state = state(
+            "ayu",
+            &[
+                ("background", Color::rgb(0x19, 0x1d, 0x26)),
+                ("foreground", Color::rgb(0xd0, 0xce, 0xc3)),
+                ("red", Color::rgb(0xea, 0x59, 0x65)),
+                ("magenta", Color::rgb(0xc9, 0xae, 0xff)),
+                ("bright_black", Color::rgb(0x55, 0x55, 0x55)),
+            ],
+        );
+        create(Vim::new(program), &state, section)
+    }
+
+    #[test]
+    fn roles() {
+        let vim = scheme(
+            "vim",
+            "vim { keyword red comment lighten(bright_black, 10%) }",
+        )
+        .unwrap();
+        let generated = vim.generated().unwrap();
+        assert!(generated.contains("set background=dark\nlet g:colors_name = \"ayu\""));
+        assert!(generated.contains("let g:terminal_color_1 = \"#ea5965\""));
+        assert!(generated.contains("hi Keyword guifg=#ea5965 ctermfg=167 gui=NONE cterm=NONE"));
+        assert!(generated.contains("hi Comment guifg=#6f6f6f ctermfg=242 gui=italic cterm=italic"));
+        assert!(generated.contains("hi Visual guibg=#47494d ctermbg=239 gui=NONE cterm=NONE"));
+        assert!(generated.contains("hi! link @keyword Keyword"));
+
+        assert_eq!(
+            scheme("vim", "vim { keyword orange }")
+                .unwrap_err()
+                .to_string(),
+            "unknown color `orange` used by `keyword`"
+        );
+    }
+
+    #[test]
+    fn lua() {
+        let generated = scheme("neovim", "neovim {}").unwrap().generated().unwrap();
+        assert!(generated.contains("vim.g.terminal_color_5 = \"#c9aeff\""));
+        assert!(generated.contains("hl(\"Keyword\", { fg = \"#c9aeff\" })"));
+        assert!(generated.contains("hl(\"Comment\", { fg = \"#555555\", italic = true })"));
+        assert!(generated.contains("hl(\"@comment\", { link = \"Comment\" })"));
+    }
+
+    #[test]
+    fn name() {
+        let named = state("Ayu Mirage/\"dark\"", &[]);
+        let vim = create(Vim::new("vim"), &named, "vim {}").unwrap();
+        assert_eq!(vim.colors_name, "ayu-mirage--dark");
+        assert!(vim.path().ends_with(".vim/colors/ayu-mirage--dark.vim"));
+        assert!(vim
+            .generated()
+            .unwrap()
+            .contains("let g:colors_name = \"ayu-mirage--dark\""));
+        assert_eq!(
+            create(Vim::new("vim"), &state("../", &[]), "vim {}")
+                .unwrap_err()
+                .to_string(),
+            "invalid theme name `../`"
+        );
+    }
+}
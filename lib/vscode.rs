@@ -0,0 +1,389 @@
+use super::{derive_shades, editor_roles, Applied, Color, Error, GetResult, Getter, State, Theme};
+use config::map::Map;
+use config::Section;
+use serde_json::{self, Value};
+use std::fs;
+use std::path::PathBuf;
+use template::Format;
+use x11::{mapped_color, ANSI};
+
+/// Roles used on top of `EDITOR_ROLES`, for the workbench and diffs.
+const ROLES: &[(&str, &[&str])] = &[
+    ("accent", &["accent", "blue"]),
+    ("panel", &["panel"]),
+    ("line", &["line"]),
+    ("border", &["border"]),
+    ("info", &["blue"]),
+    ("added", &["green"]),
+    ("changed", &["yellow"]),
+    ("removed", &["red"]),
+];
+
+const AVAILABLE_FIELDS: &[&str] = &[
+    "name",
+    "output",
+    "bg",
+    "fg",
+    "cursor",
+    "accent",
+    "surface",
+    "panel",
+    "selection",
+    "line",
+    "border",
+    "comment",
+    "keyword",
+    "string",
+    "number",
+    "function",
+    "type",
+    "variable",
+    "operator",
+    "error",
+    "warning",
+    "info",
+    "added",
+    "changed",
+    "removed",
+];
+
+/// Shades derived on top of the editor ones, `panel` is the background darkened.
+const SHADES: &[(&str, f64)] = &[("line", 0.06), ("border", 0.18)];
+
+/// Workbench colors and their role.
+const WORKBENCH: &[(&str, &str)] = &[
+    ("editor.background", "bg"),
+    ("editor.foreground", "fg"),
+    ("editorCursor.foreground", "cursor"),
+    ("editor.selectionBackground", "selection"),
+    ("editor.lineHighlightBackground", "line"),
+    ("editorLineNumber.foreground", "comment"),
+    ("editorLineNumber.activeForeground", "fg"),
+    ("editorIndentGuide.background1", "surface"),
+    ("editorWhitespace.foreground", "surface"),
+    ("editorError.foreground", "error"),
+    ("editorWarning.foreground", "warning"),
+    ("editorInfo.foreground", "info"),
+    ("editorGutter.addedBackground", "added"),
+    ("editorGutter.modifiedBackground", "changed"),
+    ("editorGutter.deletedBackground", "removed"),
+    ("editorGroupHeader.tabsBackground", "panel"),
+    ("tab.activeBackground", "bg"),
+    ("tab.activeForeground", "fg"),
+    ("tab.inactiveBackground", "panel"),
+    ("tab.inactiveForeground", "comment"),
+    ("tab.border", "border"),
+    ("activityBar.background", "panel"),
+    ("activityBar.foreground", "fg"),
+    ("activityBarBadge.background", "accent"),
+    ("activityBarBadge.foreground", "bg"),
+    ("sideBar.background", "panel"),
+    ("sideBar.foreground", "fg"),
+    ("sideBarSectionHeader.background", "panel"),
+    ("statusBar.background", "panel"),
+    ("statusBar.foreground", "fg"),
+    ("titleBar.activeBackground", "panel"),
+    ("titleBar.activeForeground", "fg"),
+    ("titleBar.inactiveBackground", "panel"),
+    ("titleBar.inactiveForeground", "comment"),
+    ("panel.background", "panel"),
+    ("panel.border", "border"),
+    ("focusBorder", "accent"),
+    ("button.background", "accent"),
+    ("button.foreground", "bg"),
+    ("badge.background", "accent"),
+    ("badge.foreground", "bg"),
+    ("input.background", "surface"),
+    ("input.foreground", "fg"),
+    ("dropdown.background", "surface"),
+    ("dropdown.foreground", "fg"),
+    ("list.activeSelectionBackground", "selection"),
+    ("list.inactiveSelectionBackground", "surface"),
+    ("list.hoverBackground", "line"),
+    ("gitDecoration.addedResourceForeground", "added"),
+    ("gitDecoration.modifiedResourceForeground", "changed"),
+    ("gitDecoration.deletedResourceForeground", "removed"),
+    ("terminal.background", "bg"),
+    ("terminal.foreground", "fg"),
+    ("terminalCursor.foreground", "cursor"),
+];
+
+/// TextMate rules with their scopes, role and font style.
+const TOKENS: &[(&str, &[&str], &str, &str)] = &[
+    (
+        "Comment",
+        &["comment", "punctuation.definition.comment"],
+        "comment",
+        "italic",
+    ),
+    ("String", &["string"], "string", ""),
+    (
+        "Number",
+        &["constant.numeric", "constant.language"],
+        "number",
+        "",
+    ),
+    (
+        "Keyword",
+        &["keyword", "storage.type", "storage.modifier"],
+        "keyword",
+        "",
+    ),
+    ("Operator", &["keyword.operator"], "operator", ""),
+    (
+        "Function",
+        &["entity.name.function", "support.function"],
+        "function",
+        "",
+    ),
+    (
+        "Type",
+        &[
+            "entity.name.type",
+            "entity.name.class",
+            "support.type",
+            "support.class",
+        ],
+        "type",
+        "",
+    ),
+    (
+        "Variable",
+        &["variable", "meta.definition.variable"],
+        "variable",
+        "",
+    ),
+    ("Invalid", &["invalid"], "error", ""),
+];
+
+/// A color theme extension for VS Code or VSCodium, written to their extensions
+/// directory.
+#[derive(Debug)]
+pub struct Vscode {
+    /// `vscode` or `vscodium`
+    program: String,
+    /// Label of the theme in the editor
+    label: String,
+    /// Extension directory
+    output: Option<PathBuf>,
+    terminal: Vec<(String, Color)>,
+    roles: Map<Color>,
+}
+
+impl Vscode {
+    pub fn new(program: &str) -> Self {
+        Vscode {
+            program: program.to_owned(),
+            label: String::new(),
+            output: None,
+            terminal: Vec::new(),
+            roles: Map::new(),
+        }
+    }
+
+    /// Label usable in extension and file names.
+    fn slug(&self) -> String {
+        let slug: String = self
+            .label
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        slug.trim_matches('-').to_owned()
+    }
+
+    fn dir(&self) -> PathBuf {
+        self.output.clone().unwrap_or_else(|| {
+            let extensions = if self.program == "vscodium" {
+                "~/.vscode-oss/extensions"
+            } else {
+                "~/.vscode/extensions"
+            };
+            ::expand_home(extensions).join(format!("themer.themer-{}-0.0.1", self.slug()))
+        })
+    }
+
+    fn dark(&self) -> bool {
+        match self.roles.get("bg") {
+            Some(bg) => bg.contrast(&Color::rgb(255, 255, 255)) > bg.contrast(&Color::rgb(0, 0, 0)),
+            None => true,
+        }
+    }
+
+    fn package(&self) -> Value {
+        json!({
+            "name": format!("themer-{}", self.slug()),
+            "displayName": self.label,
+            "description": "Generated by themer",
+            "version": "0.0.1",
+            "publisher": "themer",
+            "engines": { "vscode": "^1.60.0" },
+            "categories": ["Themes"],
+            "contributes": {
+                "themes": [{
+                    "label": self.label,
+                    "uiTheme": if self.dark() { "vs-dark" } else { "vs" },
+                    "path": format!("./themes/{}-color-theme.json", self.slug()),
+                }],
+            },
+        })
+    }
+}
+
+/// Suffix of the `terminal.ansi*` key of a palette entry, e.g. `BrightBlack` for
+/// `bright_black`.
+fn ansi_key(name: &str) -> String {
+    name.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|c| c.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+impl Theme for Vscode {
+    fn name(&self) -> &str {
+        &self.program
+    }
+
+    fn available_fields(&self) -> &[&str] {
+        AVAILABLE_FIELDS
+    }
+
+    fn create(&mut self, state: &State, section: &Section) -> Result<(), Error> {
+        self.label = match section.get_str(state, "name") {
+            GetResult::Ok(name) => name,
+            _ => state.name.to_owned(),
+        };
+        self.output = section.get_path(state, "output").to_option();
+        for name in ANSI {
+            if let Some(c) = mapped_color(state, section, name) {
+                self.terminal.push((ansi_key(name), c));
+            }
+        }
+        self.roles = editor_roles(state, section, ROLES)?;
+        derive_shades(&mut self.roles, SHADES);
+        if self.roles.get("panel").is_none() {
+            if let Some(bg) = self.roles.get("bg").cloned() {
+                let panel = bg.mix(&Color::rgb(0, 0, 0), 0.15);
+                self.roles.insert("panel".to_owned(), panel);
+            }
+        }
+        Ok(())
+    }
+
+    /// The color theme, `package.json` is written next to it by `apply`.
+    fn generated(&self) -> Result<String, Error> {
+        let mut colors = serde_json::Map::new();
+        for (key, role) in WORKBENCH {
+            if let Some(c) = self.roles.get(role) {
+                colors.insert(key.to_string(), json!(Format::HexAlpha.translucent(c)));
+            }
+        }
+        for (key, c) in &self.terminal {
+            colors.insert(
+                format!("terminal.ansi{}", key),
+                json!(Format::HexAlpha.translucent(c)),
+            );
+        }
+        let mut tokens = Vec::new();
+        for (name, scopes, role, style) in TOKENS {
+            if let Some(c) = self.roles.get(role) {
+                let mut settings = json!({ "foreground": Format::HexAlpha.translucent(c) });
+                if !style.is_empty() {
+                    settings["fontStyle"] = json!(style);
+                }
+                tokens.push(json!({ "name": name, "scope": scopes, "settings": settings }));
+            }
+        }
+        let theme = json!({
+            "name": self.label,
+            "type": if self.dark() { "dark" } else { "light" },
+            "colors": colors,
+            "tokenColors": tokens,
+        });
+        Ok(serde_json::to_string_pretty(&theme).unwrap() + "\n")
+    }
+
+    fn apply(&self) -> Result<Applied, Error> {
+        let dir = self.dir();
+        fs::create_dir_all(dir.join("themes"))?;
+        fs::write(
+            dir.join("package.json"),
+            serde_json::to_string_pretty(&self.package()).unwrap() + "\n",
+        )?;
+        fs::write(
+            dir.join("themes")
+                .join(format!("{}-color-theme.json", self.slug())),
+            self.generated()?,
+        )?;
+        // The editor picks up new extensions when it starts.
+        Ok(Applied {
+            output: dir,
+            command: None,
+            status: None,
+        })
+    }
+
+    fn output(&mut self) -> Option<&PathBuf> {
+        self.output.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use test_utils::{create, state};
+
+    fn theme(section: &str) -> Vscode {
+        let state = state(
+            "Ayu Mirage",
+            &[
+                ("background", Color::rgb(0x19, 0x1d, 0x26)),
+                ("foreground", Color::rgb(0xd0, 0xce, 0xc3)),
+                ("red", Color::rgb(0xea, 0x59, 0x65)),
+                ("magenta", Color::rgb(0xc9, 0xae, 0xff)),
+                ("bright_black", Color::rgb(0x55, 0x55, 0x55)),
+            ],
+        );
+        create(Vscode::new("vscodium"), &state, section).unwrap()
+    }
+
+    #[test]
+    fn color_theme() {
+        let vscode = theme("vscodium { keyword red }");
+        let generated: Value = serde_json::from_str(&vscode.generated().unwrap()).unwrap();
+        assert_eq!(generated["type"], "dark");
+        assert_eq!(generated["colors"]["editor.background"], "#191d26");
+        assert_eq!(generated["colors"]["editor.selectionBackground"], "#47494d");
+        assert_eq!(generated["colors"]["panel.background"], "#151920");
+        assert_eq!(generated["colors"]["terminal.ansiMagenta"], "#c9aeff");
+        assert!(generated["colors"]["activityBarBadge.background"].is_null());
+        assert_eq!(
+            generated["tokenColors"][0]["settings"]["fontStyle"],
+            "italic"
+        );
+        assert_eq!(generated["tokenColors"][1]["name"], "Keyword");
+        assert_eq!(
+            generated["tokenColors"][1]["settings"]["foreground"],
+            "#ea5965"
+        );
+    }
+
+    #[test]
+    fn package() {
+        let vscode = theme("vscodium {}");
+        assert!(vscode
+            .dir()
+            .ends_with(".vscode-oss/extensions/themer.themer-ayu-mirage-0.0.1"));
+        let package = vscode.package();
+        assert_eq!(package["contributes"]["themes"][0]["label"], "Ayu Mirage");
+        assert_eq!(
+            package["contributes"]["themes"][0]["path"],
+            "./themes/ayu-mirage-color-theme.json"
+        );
+    }
+}
@@ -1,10 +1,11 @@
-use super::{Color, Error, GetResult, Getter, State, Theme};
+use super::{run, Applied, Color, Error, GetResult, Getter, State, Theme};
 use config::map::Map;
 use config::Section;
 use std::fs::File;
 use std::io::Write;
 use std::path::PathBuf;
 use std::process::Command;
+use template::Format;
 
 const AVAILABLE_FIELDS: &[&str] = &[
     "program",
@@ -30,7 +31,27 @@ const AVAILABLE_FIELDS: &[&str] = &[
     "cursor",
 ];
 
-const COLOR_MAP: &[(&str, &str)] = &[
+/// The 16 terminal colors, the 8 normal ones followed by their bright variants.
+pub(crate) const ANSI: &[&str] = &[
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright_black",
+    "bright_red",
+    "bright_green",
+    "bright_yellow",
+    "bright_blue",
+    "bright_magenta",
+    "bright_cyan",
+    "bright_white",
+];
+
+pub(crate) const COLOR_MAP: &[(&str, &str)] = &[
     ("black", "color0"),
     ("red", "color1"),
     ("green", "color2"),
@@ -52,6 +73,17 @@ const COLOR_MAP: &[(&str, &str)] = &[
     ("cursor", "cursorColor"),
 ];
 
+/// Color given by its name or by its X11 name from `COLOR_MAP`, e.g. `color1` for
+/// `red`.
+pub(crate) fn mapped_color(state: &State, section: &Section, name: &str) -> Option<Color> {
+    section.get_color(state, name).to_option().or_else(|| {
+        COLOR_MAP
+            .iter()
+            .find(|(n, _)| *n == name)
+            .and_then(|(_, x11)| section.get_color(state, x11).to_option())
+    })
+}
+
 #[derive(Default, Debug)]
 pub struct X11 {
     program: Option<String>,
@@ -72,6 +104,10 @@ impl X11 {
 }
 
 impl Theme for X11 {
+    fn name(&self) -> &str {
+        self.program.as_deref().unwrap_or("x11")
+    }
+
     fn available_fields(&self) -> &[&str] {
         AVAILABLE_FIELDS
     }
@@ -82,11 +118,7 @@ impl Theme for X11 {
         }
         self.output = section.get_path(state, "output").to_option();
         for (color, newname) in COLOR_MAP {
-            if let Some(c) = section
-                .get_color(state, color)
-                .to_option()
-                .or(section.get_color(state, newname).to_option())
-            {
+            if let Some(c) = mapped_color(state, section, color) {
                 self.colors.insert(newname.to_string(), c);
             }
         }
@@ -94,38 +126,37 @@ impl Theme for X11 {
     }
 
     fn generated(&self) -> Result<String, Error> {
-        let program = self.program.as_ref().map(|s| &**s).unwrap_or("*");
+        let program = self.program.as_deref().unwrap_or("*");
         let mut buf = Vec::new();
         for entry in &self.colors {
             let name = &entry.name;
             let value = &entry.value;
             buf.push(format!(
-                "{}.{}: #{:02x}{:02x}{:02x}",
-                program, name, value.0, value.1, value.2
+                "{}.{}: {}",
+                program,
+                name,
+                Format::Hex.format(value)
             ));
         }
         Ok(buf.join("\n"))
     }
 
-    fn apply(&self) -> Result<(), Error> {
+    fn apply(&self) -> Result<Applied, Error> {
         let generated = self.generated()?;
-        let path;
-        let mut f = match self.output {
-            Some(ref p) => {
-                path = p.display().to_string();
-                File::create(p)?
-            }
+        let path = match self.output {
+            Some(ref p) => p.to_owned(),
             None => {
-                let program = self.program.as_ref().map(|s| &**s).unwrap_or("default");
-                let p = ::std::env::temp_dir().join(format!("themer-x11_{}", program));
-                path = p.display().to_string();
-                File::create(p)?
+                let program = self.program.as_deref().unwrap_or("default");
+                ::std::env::temp_dir().join(format!("themer-x11_{}", program))
             }
         };
-        f.write_all(generated.as_bytes())?;
-        let command = Command::new("xrdb").arg("-merge").arg(&path).output()?;
-        println!("{:?}", command);
-        Ok(())
+        File::create(&path)?.write_all(generated.as_bytes())?;
+        let status = run(Command::new("xrdb").arg("-merge").arg(&path))?;
+        Ok(Applied {
+            command: Some(format!("xrdb -merge {}", path.display())),
+            output: path,
+            status: Some(status),
+        })
     }
 
     fn output(&mut self) -> Option<&PathBuf> {
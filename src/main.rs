@@ -1,9 +1,26 @@
+#[macro_use]
+extern crate serde_json;
 extern crate structopt;
 extern crate themer;
-extern crate themer_config as config;
+#[cfg(test)]
+extern crate themer_config;
 
-use std::path::PathBuf;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use structopt::StructOpt;
+use themer::lint::{Check, Options};
+use themer::template::{Format, Registry};
+use themer::SectionTheme;
+
+#[derive(StructOpt, Debug)]
+struct Use {
+    /// Name of the theme, without the `.th` extension
+    #[structopt(name = "theme")]
+    name: String,
+    /// Directory containing the themes
+    #[structopt(long = "themes", parse(from_os_str))]
+    themes: Option<PathBuf>,
+}
 
 #[derive(StructOpt, Debug)]
 struct Generate {
@@ -11,17 +28,62 @@ struct Generate {
     file: PathBuf,
 }
 
+#[derive(StructOpt, Debug)]
+struct Apply {
+    /// Name of the theme, the current one by default
+    #[structopt(name = "theme")]
+    name: Option<String>,
+    /// Directory containing the themes
+    #[structopt(long = "themes", parse(from_os_str))]
+    themes: Option<PathBuf>,
+}
+
+#[derive(StructOpt, Debug)]
+struct Preview {
+    /// Name of the theme, the current one by default
+    #[structopt(name = "theme")]
+    name: Option<String>,
+    /// Directory containing the themes
+    #[structopt(long = "themes", parse(from_os_str))]
+    themes: Option<PathBuf>,
+}
+
+#[derive(StructOpt, Debug)]
+struct Lint {
+    /// Name of the theme, the current one by default
+    #[structopt(name = "theme")]
+    name: Option<String>,
+    /// Directory containing the themes
+    #[structopt(long = "themes", parse(from_os_str))]
+    themes: Option<PathBuf>,
+    /// Minimum WCAG 2.1 contrast ratio
+    #[structopt(long = "threshold", default_value = "4.5")]
+    threshold: f64,
+    /// Also require this absolute APCA lightness contrast, e.g. 60
+    #[structopt(long = "apca")]
+    apca: Option<f64>,
+    /// Print every check as JSON
+    #[structopt(long = "json")]
+    json: bool,
+}
+
 #[derive(StructOpt, Debug)]
 enum Command {
     /// Use specified theme
     #[structopt(name = "use")]
-    Use,
+    Use(Use),
     /// Generate a new theme
     #[structopt(name = "generate")]
     Generate(Generate),
-    /// Apply theme (needed for Xresources or else
+    /// Apply theme: write every section and reload the programs using it
     #[structopt(name = "apply")]
-    Apply,
+    Apply(Apply),
+    /// Check the contrast of the theme colors against the background
+    #[structopt(name = "lint")]
+    Lint(Lint),
+    /// Show the colors of a theme without applying it
+    #[structopt(name = "preview")]
+    Preview(Preview),
 }
 
 #[derive(StructOpt, Debug)]
@@ -31,38 +93,365 @@ struct Themer {
     command: Command,
     #[structopt(short = "c", long = "config", parse(from_os_str))]
     config: Option<PathBuf>,
+    /// Additional template directory, searched before the default ones
+    #[structopt(long = "templates", parse(from_os_str))]
+    templates: Option<PathBuf>,
+}
+
+/// Load templates from `--templates`, the user directory and then the system one.
+fn load_templates(home: &str, extra: Option<PathBuf>) -> Result<Registry, themer::Error> {
+    let mut dirs: Vec<PathBuf> = extra.into_iter().collect();
+    dirs.push(PathBuf::from(format!("{}/.config/themer/templates", home)));
+    dirs.push(PathBuf::from("/usr/share/themer/templates"));
+    let templates = Registry::scan_dirs(&dirs)?;
+    for conflict in templates.conflicts() {
+        eprintln!("warning: {}", conflict);
+    }
+    Ok(templates)
+}
+
+/// Apply every theme, reporting each target and the sections which could not be
+/// processed. Returns false if any of them failed.
+fn apply_themes<W: Write>(out: &mut W, themes: Vec<SectionTheme>) -> Result<bool, themer::Error> {
+    let mut ok = true;
+    for theme in themes {
+        let theme = match theme {
+            Ok(theme) => theme,
+            Err(e) => {
+                writeln!(out, "error: {}", e)?;
+                ok = false;
+                continue;
+            }
+        };
+        match theme.apply() {
+            Ok(applied) => {
+                write!(out, "{}: wrote {}", theme.name(), applied.output.display())?;
+                if let Some(ref command) = applied.command {
+                    write!(out, ", ran `{}`", command)?;
+                }
+                match applied.status.and_then(|s| s.code()) {
+                    Some(code) => writeln!(out, " (exit {})", code)?,
+                    None if applied.status.is_some() => writeln!(out, " (killed by signal)")?,
+                    None => writeln!(out)?,
+                }
+                ok &= applied.success();
+            }
+            Err(e) => {
+                writeln!(out, "{}: error: {}", theme.name(), e)?;
+                ok = false;
+            }
+        }
+    }
+    Ok(ok)
+}
+
+/// Directory of the themes, `~/.config/themer/themes` unless given.
+fn themes_dir(home: &str, themes: Option<PathBuf>) -> PathBuf {
+    themes.unwrap_or(PathBuf::from(format!("{}/.config/themer/themes", home)))
+}
+
+/// File recording the theme last used.
+fn current_path(home: &str) -> PathBuf {
+    PathBuf::from(format!("{}/.config/themer/current", home))
+}
+
+fn use_theme(home: &str, opts: Use, templates: &Registry) -> Result<bool, themer::Error> {
+    let path = themer::find_theme(&[themes_dir(home, opts.themes)], &opts.name)?;
+    let mut config = themer::load_config(path)?;
+    let themes = themer::process_config(&mut config, &opts.name, templates)?;
+    let ok = apply_themes(&mut io::stdout(), themes)?;
+    if ok {
+        themer::set_current_theme(current_path(home), &opts.name)?;
+    }
+    Ok(ok)
+}
+
+/// Name of a theme from its config file, e.g. `ayu_mirage` for `themes/ayu_mirage.th`.
+fn theme_name(config: &Path) -> String {
+    config
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "default".to_owned())
+}
+
+/// Config of the theme to act on, with its name: the theme given by name, else the
+/// `--config` file, else the current theme and finally `default.th`.
+fn theme_config(
+    home: &str,
+    config: Option<PathBuf>,
+    name: Option<String>,
+    themes: Option<PathBuf>,
+) -> Result<(PathBuf, String), themer::Error> {
+    let name = match name {
+        Some(name) => Some(name),
+        None if config.is_some() => None,
+        None => themer::current_theme(current_path(home)),
+    };
+    match (name, config) {
+        (Some(name), _) => Ok((
+            themer::find_theme(&[themes_dir(home, themes)], &name)?,
+            name,
+        )),
+        (None, Some(config)) => {
+            let name = theme_name(&config);
+            Ok((config, name))
+        }
+        (None, None) => {
+            let config = PathBuf::from(format!("{}/.config/themer/default.th", home));
+            let name = theme_name(&config);
+            Ok((config, name))
+        }
+    }
+}
+
+fn apply(config: PathBuf, name: &str, templates: &Registry) -> Result<bool, themer::Error> {
+    let mut config = themer::load_config(config)?;
+    let themes = themer::process_config(&mut config, name, templates)?;
+    apply_themes(&mut io::stdout(), themes)
+}
+
+fn preview(config: PathBuf, name: &str) -> Result<bool, themer::Error> {
+    let config = themer::load_config(config)?;
+    let state = themer::process_state(&config, name)?;
+    print!(
+        "{}",
+        themer::preview::render(&state, themer::preview::truecolor())
+    );
+    Ok(true)
+}
+
+fn check_json(check: &Check) -> serde_json::Value {
+    json!({
+        "foreground": check.foreground,
+        "background": check.background,
+        "foreground_color": Format::Hex.format(&check.foreground_color),
+        "background_color": Format::Hex.format(&check.background_color),
+        "contrast": check.contrast,
+        "apca": check.apca,
+        "passed": check.passed,
+        "suggestion": check.suggestion.as_ref().map(|c| Format::Hex.format(c)),
+    })
+}
+
+/// Report the checks that failed, or all of them as JSON. Returns false if any failed.
+fn lint(config: PathBuf, name: &str, opts: Lint) -> Result<bool, themer::Error> {
+    let config = themer::load_config(config)?;
+    let state = themer::process_state(&config, name)?;
+    let mut options = Options {
+        threshold: opts.threshold,
+        apca: opts.apca,
+        skip: Vec::new(),
+    };
+    if let Some(section) = config.sections().get("lint") {
+        options.configure(&state, section);
+    }
+    let checks = themer::lint::lint(&state, &options)?;
+    if opts.json {
+        let checks: Vec<_> = checks.iter().map(check_json).collect();
+        let report = json!({
+            "theme": name,
+            "threshold": options.threshold,
+            "apca": options.apca,
+            "skip": options.skip,
+            "checks": checks,
+        });
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        for check in checks.iter().filter(|c| !c.passed) {
+            print!(
+                "{} {} on {} {}: {:.2}:1",
+                check.foreground,
+                Format::Hex.format(&check.foreground_color),
+                check.background,
+                Format::Hex.format(&check.background_color),
+                check.contrast
+            );
+            if let Some(apca) = check.apca {
+                print!(", APCA Lc {:.1}", apca);
+            }
+            match check.suggestion {
+                Some(ref c) => println!(", try {}", Format::Hex.format(c)),
+                None => println!(", no color of the same hue passes"),
+            }
+        }
+    }
+    Ok(checks.iter().all(|c| c.passed))
+}
+
+fn exit_with(result: Result<bool, themer::Error>) {
+    match result {
+        Ok(true) => {}
+        Ok(false) => ::std::process::exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            ::std::process::exit(1);
+        }
+    }
 }
 
 fn main() {
     let themer = Themer::from_args();
     let home = ::std::env::var("HOME").unwrap();
-    let config = themer
-        .config
-        .unwrap_or(PathBuf::from(format!("{}/.config/themer/default.th", home)));
-    let config = config::read_config(config).unwrap().unwrap();
-    //let themes = match themer::process_config(&mut config) {
-    //    Ok(themes) => themes,
-    //    Err(e) => {
-    //        println!("{}", e);
-    //        return;
-    //    }
-    //};
+    let config = themer.config;
+    let templates = themer.templates;
     match themer.command {
-        //Command::Apply => {
-        //    for theme in themes {
-        //        theme.apply().unwrap();
-        //    }
-        //}
+        Command::Apply(opts) => exit_with(
+            theme_config(&home, config, opts.name, opts.themes).and_then(|(config, name)| {
+                load_templates(&home, templates).and_then(|t| apply(config, &name, &t))
+            }),
+        ),
+        Command::Lint(opts) => exit_with(
+            theme_config(&home, config, opts.name.clone(), opts.themes.clone())
+                .and_then(|(config, name)| lint(config, &name, opts)),
+        ),
+        Command::Preview(opts) => exit_with(
+            theme_config(&home, config, opts.name, opts.themes)
+                .and_then(|(config, name)| preview(config, &name)),
+        ),
+        Command::Use(opts) => {
+            exit_with(load_templates(&home, templates).and_then(|t| use_theme(&home, opts, &t)))
+        }
         Command::Generate(gen) => {
-            use std::io::Read;
-            let mut file = std::fs::File::open(gen.file).unwrap();
-            let mut buf = String::new();
-            file.read_to_string(&mut buf).unwrap();
-            let template = themer::template::Parser::new(&buf).parse().unwrap();
-            let state = themer::process_state(&config);
-            let result = themer::template::process_parts(template.parts, &state.colors);
-            println!("{}", result.unwrap());
+            let config =
+                config.unwrap_or(PathBuf::from(format!("{}/.config/themer/default.th", home)));
+            let name = theme_name(&config);
+            let (config, template) = match themer::load_config(config)
+                .and_then(|c| themer::template::read_template(gen.file).map(|t| (c, t)))
+            {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    ::std::process::exit(1);
+                }
+            };
+            let state = match themer::process_state(&config, &name) {
+                Ok(state) => state,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    ::std::process::exit(1);
+                }
+            };
+            let vars = vec![("theme_name".to_owned(), name)].into_iter().collect();
+            match themer::template::process_parts(&template.parts, &state.colors, &vars) {
+                Ok(result) => println!("{}", result),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    ::std::process::exit(1);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+    use themer::{Applied, Theme};
+    use themer_config::Section;
+
+    /// Target whose apply reports the exit status given, or fails to start `missing`.
+    #[derive(Debug)]
+    struct Fake(Option<i32>);
+
+    impl Theme for Fake {
+        fn name(&self) -> &str {
+            "fake"
+        }
+
+        fn available_fields(&self) -> &[&str] {
+            &[]
+        }
+
+        fn create(&mut self, _: &themer::State, _: &Section) -> Result<(), themer::Error> {
+            Ok(())
+        }
+
+        fn generated(&self) -> Result<String, themer::Error> {
+            Ok(String::new())
         }
-        _c => unimplemented!(),
+
+        fn apply(&self) -> Result<Applied, themer::Error> {
+            match self.0 {
+                Some(code) => Ok(Applied {
+                    output: PathBuf::from("/tmp/fake"),
+                    command: Some("reload".to_owned()),
+                    status: Some(ExitStatus::from_raw(code << 8)),
+                }),
+                None => Err(themer::Error::Command(
+                    "missing".to_owned(),
+                    io::Error::from(io::ErrorKind::NotFound),
+                )),
+            }
+        }
+
+        fn output(&mut self) -> Option<&PathBuf> {
+            None
+        }
+    }
+
+    fn report(themes: Vec<SectionTheme>) -> (bool, String) {
+        let mut out = Vec::new();
+        let ok = apply_themes(&mut out, themes).unwrap();
+        (ok, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn summary() {
+        assert_eq!(
+            report(vec![Ok(Box::new(Fake(Some(0))))]),
+            (
+                true,
+                "fake: wrote /tmp/fake, ran `reload` (exit 0)\n".to_owned()
+            )
+        );
+        let (ok, out) = report(vec![
+            Err(themer::Error::Section(
+                "kitty".to_owned(),
+                Box::new(themer::Error::UnknownFunction("foo".to_owned())),
+            )),
+            Ok(Box::new(Fake(None))),
+            Ok(Box::new(Fake(Some(1)))),
+        ]);
+        assert!(!ok);
+        assert_eq!(
+            out,
+            "\
+error: kitty: unknown function `foo`
+fake: error: could not run `missing`: entity not found
+fake: wrote /tmp/fake, ran `reload` (exit 1)
+"
+        );
+    }
+
+    #[test]
+    fn current_theme_is_the_default() {
+        let home = ::std::env::temp_dir().join(format!("themer-main-{}", ::std::process::id()));
+        fs::create_dir_all(home.join(".config/themer/themes")).unwrap();
+        fs::write(home.join(".config/themer/themes/ayu.th"), "").unwrap();
+        let home_str = home.to_str().unwrap();
+
+        let (config, name) = theme_config(home_str, None, None, None).unwrap();
+        assert_eq!(config, home.join(".config/themer/default.th"));
+        assert_eq!(name, "default");
+
+        themer::set_current_theme(current_path(home_str), "ayu").unwrap();
+        let (config, name) = theme_config(home_str, None, None, None).unwrap();
+        assert_eq!(config, home.join(".config/themer/themes/ayu.th"));
+        assert_eq!(name, "ayu");
+
+        let explicit = PathBuf::from("/tmp/other.th");
+        let (config, name) = theme_config(home_str, Some(explicit.clone()), None, None).unwrap();
+        assert_eq!((config, name.as_str()), (explicit, "other"));
+
+        assert_eq!(
+            theme_config(home_str, None, Some("missing".to_owned()), None)
+                .unwrap_err()
+                .to_string(),
+            "theme `missing` not found"
+        );
+        fs::remove_dir_all(home).unwrap();
     }
 }